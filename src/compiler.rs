@@ -1,12 +1,13 @@
 use crate::chunk::Chunk;
-use crate::errors::error;
 use crate::parser::Parser;
 use crate::rules::{ParseFn, ParseRule, Precedence};
 use crate::scanner::Scanner;
 use crate::tokens::{Token, TokenType};
-use crate::values::GenericValue;
+use crate::types::Type;
+use crate::values::{Enum, EnumVariant, GenericValue, MatchPattern, RecordType};
 use crate::vm::disassemble_chunk;
 use crate::vm::OpCode;
+use std::mem;
 
 /*
  *
@@ -19,8 +20,18 @@ use crate::vm::OpCode;
  */
 
 pub fn compile(s: String, chunk: &mut Chunk) -> bool {
+    compile_source(s, chunk, false)
+}
+
+pub fn compile_with_types(s: String, chunk: &mut Chunk) -> bool {
+    // compile() that also reports every type annotation mismatch
+    compile_source(s, chunk, true)
+}
+
+fn compile_source(s: String, chunk: &mut Chunk, check_types: bool) -> bool {
     let mut scanner = Scanner::new(s);
     let mut parser = Parser::new();
+    parser.check_types = check_types;
     parser.advance(&mut scanner); // Not sure why do we need this, instead of initialize previous as None, and current is the first token ..., maybe there are reasons in the book
     while !match_token(&mut parser, &mut scanner, TokenType::EOF) {
         declaration(&mut parser, &mut scanner, chunk);
@@ -45,13 +56,33 @@ fn check(expect_token_type: &TokenType, current_token_type: &TokenType) -> bool
 pub fn declaration(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
     if match_token(parser, scanner, TokenType::Var) {
         var_declaration(parser, scanner, chunk)
+    } else if match_token(parser, scanner, TokenType::Enum) {
+        enum_declaration(parser, scanner, chunk)
+    } else if match_token(parser, scanner, TokenType::Record) {
+        record_declaration(parser, scanner, chunk)
+    } else if match_token(parser, scanner, TokenType::Import) {
+        import_declaration(parser, scanner, chunk)
+    } else if match_token(parser, scanner, TokenType::From) {
+        from_declaration(parser, scanner, chunk)
     } else {
         statement(parser, scanner, chunk);
     }
 }
 
 fn var_declaration(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
+    let current_type = *parser.current.as_ref().unwrap().get_type();
+    if check(&TokenType::LeftParen, &current_type) || check(&TokenType::LeftBracket, &current_type)
+    {
+        destructuring_declaration(parser, scanner, chunk);
+        return;
+    }
     let global_var = parse_variable(parser, scanner, chunk, "Expect variable name");
+    let name = parser.previous.as_ref().unwrap().get_lexeme();
+    let annotation = if match_token(parser, scanner, TokenType::Colon) {
+        Some(type_annotation(parser, scanner))
+    } else {
+        None
+    };
     if match_token(parser, scanner, TokenType::Equal) {
         expression(parser, scanner, chunk);
     } else {
@@ -64,6 +95,7 @@ fn var_declaration(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk
                 .expect("previous token in val declaration should not be none")
                 .get_line(),
         );
+        parser.expr_type = Type::Nil;
     }
     parser.consume(
         TokenType::Semicolon,
@@ -71,6 +103,379 @@ fn var_declaration(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk
         "Expect ';' after variable declaration",
     );
     define_variable(global_var, parser, chunk);
+    let line = parser.previous.as_ref().unwrap().get_line();
+    let value_type = mem::take(&mut parser.expr_type);
+    match annotation {
+        Some(ty) => {
+            if !ty.accepts(&value_type) {
+                let msg = format!("cannot assign {} to {}: {}", value_type, name, ty);
+                parser.type_error(line, &msg);
+            }
+            parser.define_global_type(name, ty, true);
+        }
+        None => parser.define_global_type(name, value_type, false),
+    }
+}
+
+fn type_annotation(parser: &mut Parser, scanner: &mut Scanner) -> Type {
+    // `Int`, `Str?` or the name of an enum, checked by `lolang check --types` only
+    parser.consume(TokenType::Identifier, scanner, "Expect type name");
+    let token = parser.previous.clone().unwrap();
+    let name = token.get_lexeme();
+    let ty = Type::from_name(&name, &parser.type_names).unwrap_or_else(|| {
+        parser.type_error(token.get_line(), &format!("unknown type {}", name));
+        Type::Any
+    });
+    if match_token(parser, scanner, TokenType::QuestionMark) {
+        Type::Optional(Box::new(ty))
+    } else {
+        ty
+    }
+}
+
+fn enum_declaration(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
+    // enum Shape { Circle(r), Rect(w, h), Empty }
+    let global_var = parse_variable(parser, scanner, chunk, "Expect enum name");
+    let name = parser.previous.as_ref().unwrap().get_lexeme();
+    parser.consume(TokenType::LeftBrace, scanner, "Expect '{' before enum body");
+
+    let mut variants: Vec<EnumVariant> = Vec::new();
+    while !check(
+        &TokenType::RightBrace,
+        parser.current.as_ref().unwrap().get_type(),
+    ) {
+        parser.consume(TokenType::Identifier, scanner, "Expect variant name");
+        let token = parser.previous.clone().unwrap();
+        if variants.iter().any(|v| v.name == token.get_lexeme()) {
+            parser.error_at(&token, "Duplicate variant name in enum");
+        }
+        let mut fields = Vec::new();
+        if match_token(parser, scanner, TokenType::LeftParen) {
+            if !check(
+                &TokenType::RightParen,
+                parser.current.as_ref().unwrap().get_type(),
+            ) {
+                loop {
+                    parser.consume(TokenType::Identifier, scanner, "Expect field name");
+                    fields.push(parser.previous.as_ref().unwrap().get_lexeme());
+                    if !match_token(parser, scanner, TokenType::Comma) {
+                        break;
+                    }
+                }
+            }
+            parser.consume(
+                TokenType::RightParen,
+                scanner,
+                "Expect ')' after variant fields",
+            );
+        }
+        variants.push(EnumVariant {
+            name: token.get_lexeme(),
+            fields,
+        });
+        if !match_token(parser, scanner, TokenType::Comma) {
+            break;
+        }
+    }
+    parser.consume(TokenType::RightBrace, scanner, "Expect '}' after enum body");
+
+    parser.type_names.insert(name.clone());
+    parser.enums.insert(
+        name.clone(),
+        variants
+            .iter()
+            .map(|v| (v.name.clone(), v.fields.len()))
+            .collect(),
+    );
+    let line = parser.previous.as_ref().unwrap().get_line();
+    emit_constant(
+        line,
+        GenericValue::from_enum(Enum { name, variants }),
+        chunk,
+    );
+    define_variable(global_var, parser, chunk);
+}
+
+fn record_declaration(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
+    // record Point(x, y)
+    let global_var = parse_variable(parser, scanner, chunk, "Expect record name");
+    let name = parser.previous.as_ref().unwrap().get_lexeme();
+    parser.consume(
+        TokenType::LeftParen,
+        scanner,
+        "Expect '(' after record name",
+    );
+
+    let mut fields: Vec<String> = Vec::new();
+    if !check(
+        &TokenType::RightParen,
+        parser.current.as_ref().unwrap().get_type(),
+    ) {
+        loop {
+            parser.consume(TokenType::Identifier, scanner, "Expect field name");
+            let token = parser.previous.clone().unwrap();
+            if fields.contains(&token.get_lexeme()) {
+                parser.error_at(&token, "Duplicate field name in record");
+            }
+            fields.push(token.get_lexeme());
+            if !match_token(parser, scanner, TokenType::Comma) {
+                break;
+            }
+        }
+    }
+    parser.consume(
+        TokenType::RightParen,
+        scanner,
+        "Expect ')' after record fields",
+    );
+    match_token(parser, scanner, TokenType::Semicolon);
+    parser.type_names.insert(name.clone());
+
+    let line = parser.previous.as_ref().unwrap().get_line();
+    emit_constant(
+        line,
+        GenericValue::from_record_type(RecordType { name, fields }),
+        chunk,
+    );
+    define_variable(global_var, parser, chunk);
+}
+
+fn import_declaration(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
+    // import "lib/shapes.lo" as shapes;
+    let path = import_path(parser, scanner, chunk);
+    parser.consume(TokenType::As, scanner, "Expect 'as' after import path");
+    let global_var = parse_variable(parser, scanner, chunk, "Expect module name");
+    parser.consume(TokenType::Semicolon, scanner, "Expect ';' after import");
+    let line = parser.previous.as_ref().unwrap().get_line();
+    emit_bytes(chunk, OpCode::OpImport as usize, path, line);
+    define_variable(global_var, parser, chunk);
+}
+
+fn from_declaration(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
+    // from "lib/shapes.lo" import area, Shape;
+    let path = import_path(parser, scanner, chunk);
+    parser.consume(
+        TokenType::Import,
+        scanner,
+        "Expect 'import' after module path",
+    );
+    let mut names = Vec::new();
+    loop {
+        names.push(parse_variable(
+            parser,
+            scanner,
+            chunk,
+            "Expect name to import",
+        ));
+        if !match_token(parser, scanner, TokenType::Comma) {
+            break;
+        }
+    }
+    parser.consume(TokenType::Semicolon, scanner, "Expect ';' after import");
+    let line = parser.previous.as_ref().unwrap().get_line();
+    emit_bytes(chunk, OpCode::OpImport as usize, path, line);
+    for name in names {
+        // the checker can't see into the module, any imported name may be an enum or a record
+        let type_name = chunk.const_pool.values[name].as_string().unwrap();
+        parser.type_names.insert(type_name);
+        emit_bytes(chunk, OpCode::OpImportName as usize, name, line);
+        define_variable(name, parser, chunk);
+    }
+    emit_byte(chunk, OpCode::OpPop as usize, line);
+}
+
+fn import_path(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) -> usize {
+    parser.consume(TokenType::String, scanner, "Expect module path string");
+    let path = parser.previous.as_ref().unwrap().get_lexeme();
+    make_constant(GenericValue::from_string(path), chunk)
+}
+
+// The target of a destructuring: `x`, `(a, b)` or `[first, ...rest]`
+enum Pattern {
+    Name(usize), // constant index of the variable name
+    Sequence {
+        elements: Vec<Pattern>,
+        rest: Option<usize>, // position of the `...name` element
+    },
+}
+
+fn destructuring_declaration(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
+    // var (x, y) = point;
+    let pattern = parse_pattern(parser, scanner, chunk);
+    parser.consume(
+        TokenType::Equal,
+        scanner,
+        "Expect '=' after destructuring pattern",
+    );
+    expression(parser, scanner, chunk);
+    parser.consume(
+        TokenType::Semicolon,
+        scanner,
+        "Expect ';' after variable declaration",
+    );
+    let line = parser.previous.as_ref().unwrap().get_line();
+    emit_destructure(&pattern, parser, chunk, true, line);
+}
+
+fn destructuring_assignment(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
+    // (x, y) = (y, x);
+    let pattern = parse_pattern(parser, scanner, chunk);
+    parser.consume(
+        TokenType::Equal,
+        scanner,
+        "Expect '=' after destructuring pattern",
+    );
+    expression(parser, scanner, chunk);
+    parser.consume(TokenType::Semicolon, scanner, "Expect ';' after expression");
+    let line = parser.previous.as_ref().unwrap().get_line();
+    emit_destructure(&pattern, parser, chunk, false, line);
+}
+
+fn is_destructuring_assignment(parser: &Parser, scanner: &mut Scanner) -> bool {
+    /*
+       A statement starting with '(' or '[' is either an expression or a
+       destructuring assignment. Scan ahead without compiling anything: it
+       is an assignment if a whole pattern is followed by '='.
+    */
+    let mut token = parser.current.clone().unwrap();
+    if !matches!(
+        token.get_type(),
+        TokenType::LeftParen | TokenType::LeftBracket
+    ) {
+        return false;
+    }
+    let state = scanner.save();
+    let is_assignment = skip_pattern(&mut token, scanner) && token.get_type() == &TokenType::Equal;
+    scanner.restore(state);
+    is_assignment
+}
+
+fn skip_pattern(token: &mut Token, scanner: &mut Scanner) -> bool {
+    // on success `token` is the first token after the pattern
+    let close = match token.get_type() {
+        TokenType::Identifier => {
+            *token = scanner.scan_token();
+            return true;
+        }
+        TokenType::LeftParen => TokenType::RightParen,
+        TokenType::LeftBracket => TokenType::RightBracket,
+        _ => return false,
+    };
+    *token = scanner.scan_token();
+    if token.get_type() != &close {
+        loop {
+            if token.get_type() == &TokenType::DotDotDot {
+                *token = scanner.scan_token();
+                if token.get_type() != &TokenType::Identifier {
+                    return false;
+                }
+                *token = scanner.scan_token();
+            } else if !skip_pattern(token, scanner) {
+                return false;
+            }
+            if token.get_type() != &TokenType::Comma {
+                break;
+            }
+            *token = scanner.scan_token();
+        }
+    }
+    if token.get_type() != &close {
+        return false;
+    }
+    *token = scanner.scan_token();
+    true
+}
+
+fn parse_pattern(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) -> Pattern {
+    if match_token(parser, scanner, TokenType::Identifier) {
+        return Pattern::Name(identifier_constant(parser.previous.as_ref(), chunk));
+    }
+    let (close, msg) = if match_token(parser, scanner, TokenType::LeftParen) {
+        (
+            TokenType::RightParen,
+            "Expect ')' after destructuring pattern",
+        )
+    } else if match_token(parser, scanner, TokenType::LeftBracket) {
+        (
+            TokenType::RightBracket,
+            "Expect ']' after destructuring pattern",
+        )
+    } else {
+        let token = parser.current.clone().unwrap();
+        parser.error_at(&token, "Expect variable name or destructuring pattern");
+        return Pattern::Sequence {
+            elements: Vec::new(),
+            rest: None,
+        };
+    };
+
+    let mut elements = Vec::new();
+    let mut rest = None;
+    if !check(&close, parser.current.as_ref().unwrap().get_type()) {
+        loop {
+            if match_token(parser, scanner, TokenType::DotDotDot) {
+                if rest.is_some() {
+                    let token = parser.previous.clone().unwrap();
+                    parser.error_at(&token, "Only one rest element is allowed in a pattern");
+                }
+                rest = Some(elements.len());
+                parser.consume(
+                    TokenType::Identifier,
+                    scanner,
+                    "Expect variable name after '...'",
+                );
+                elements.push(Pattern::Name(identifier_constant(
+                    parser.previous.as_ref(),
+                    chunk,
+                )));
+            } else {
+                elements.push(parse_pattern(parser, scanner, chunk));
+            }
+            if !match_token(parser, scanner, TokenType::Comma) {
+                break;
+            }
+        }
+    }
+    parser.consume(close, scanner, msg);
+    Pattern::Sequence { elements, rest }
+}
+
+fn emit_destructure(
+    pattern: &Pattern,
+    parser: &mut Parser,
+    chunk: &mut Chunk,
+    define: bool,
+    line: usize,
+) {
+    /*
+       The unpack opcodes replace the value on top of the stack with its
+       elements, the last one on top, so the targets are bound back to front.
+    */
+    match pattern {
+        Pattern::Name(name) => {
+            if define {
+                emit_bytes(chunk, OpCode::OpDefineGlobal as usize, *name, line);
+                forget_global_type(*name, parser, chunk);
+            } else {
+                emit_bytes(chunk, OpCode::OpSetGlobal as usize, *name, line);
+                emit_byte(chunk, OpCode::OpPop as usize, line);
+                let name = chunk.const_pool.values[*name].as_string().unwrap();
+                parser.assign_global_type(&name, Type::Any, line);
+            }
+        }
+        Pattern::Sequence { elements, rest } => {
+            match rest {
+                None => emit_bytes(chunk, OpCode::OpUnpack as usize, elements.len(), line),
+                Some(i) => {
+                    emit_bytes(chunk, OpCode::OpUnpackRest as usize, *i, line);
+                    emit_byte(chunk, elements.len() - i - 1, line);
+                }
+            }
+            for element in elements.iter().rev() {
+                emit_destructure(element, parser, chunk, define, line);
+            }
+        }
+    }
 }
 
 fn parse_variable(
@@ -89,6 +494,7 @@ fn identifier_constant(previous_token: Option<&Token>, chunk: &mut Chunk) -> usi
 }
 
 fn define_variable(global_var: usize, parser: &mut Parser, chunk: &mut Chunk) {
+    forget_global_type(global_var, parser, chunk);
     emit_bytes(
         chunk,
         OpCode::OpDefineGlobal as usize,
@@ -101,14 +507,93 @@ fn define_variable(global_var: usize, parser: &mut Parser, chunk: &mut Chunk) {
     );
 }
 
+fn forget_global_type(global_var: usize, parser: &mut Parser, chunk: &Chunk) {
+    // a variable (re)defined with a value the type checker doesn't know, a
+    // new definition drops the annotation like `var x = ...;` does
+    let name = chunk.const_pool.values[global_var]
+        .as_string()
+        .expect("variable name constant should be a string");
+    parser.define_global_type(name, Type::Any, false);
+}
+
 fn statement(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
     if match_token(parser, scanner, TokenType::Print) {
         print_statement(parser, scanner, chunk);
+    } else if match_token(parser, scanner, TokenType::For) {
+        for_statement(parser, scanner, chunk);
+    } else if match_token(parser, scanner, TokenType::LeftBrace) {
+        block(parser, scanner, chunk);
+    } else if is_destructuring_assignment(parser, scanner) {
+        destructuring_assignment(parser, scanner, chunk);
     } else {
         expression_statement(parser, scanner, chunk)
     }
 }
 
+fn block(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
+    while !check(
+        &TokenType::RightBrace,
+        parser.current.as_ref().unwrap().get_type(),
+    ) && !check(&TokenType::EOF, parser.current.as_ref().unwrap().get_type())
+    {
+        declaration(parser, scanner, chunk);
+    }
+    parser.consume(TokenType::RightBrace, scanner, "Expect '}' after block");
+}
+
+fn for_statement(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
+    /*
+       for (x in iterable) body
+
+       OpIterInit swaps the iterable for an iterator kept on the stack while
+       the loop runs. There are no local scopes yet, so the loop variable is
+       (re)defined as a global before every pass over the body.
+
+               <iterable>
+               OpIterInit
+       start:  OpIterNext   exit
+               OpDefineGlobal x
+               <body>
+               OpLoop       start
+       exit:   OpPop
+    */
+    parser.consume(TokenType::LeftParen, scanner, "Expect '(' after 'for'");
+    let declares = match_token(parser, scanner, TokenType::Var);
+    let name = parse_variable(parser, scanner, chunk, "Expect loop variable name");
+    parser.consume(TokenType::In, scanner, "Expect 'in' after loop variable");
+    expression(parser, scanner, chunk);
+    let element_type = match mem::take(&mut parser.expr_type) {
+        Type::Str => Type::Str,
+        Type::Bytes => Type::Int,
+        _ => Type::Any,
+    };
+    parser.consume(
+        TokenType::RightParen,
+        scanner,
+        "Expect ')' after for clauses",
+    );
+    let line = parser.previous.as_ref().unwrap().get_line();
+
+    emit_byte(chunk, OpCode::OpIterInit as usize, line);
+    let loop_start = chunk.count;
+    let exit_jump = emit_jump(chunk, OpCode::OpIterNext, line);
+    emit_bytes(chunk, OpCode::OpDefineGlobal as usize, name, line);
+    if declares {
+        forget_global_type(name, parser, chunk);
+    } else {
+        // `for (x in ...)` assigns every element to x, an annotated x checks them
+        let name = chunk.const_pool.values[name].as_string().unwrap();
+        parser.assign_global_type(&name, element_type, line);
+    }
+
+    statement(parser, scanner, chunk);
+
+    let line = parser.previous.as_ref().unwrap().get_line();
+    emit_loop(chunk, loop_start, line);
+    patch_jump(chunk, exit_jump);
+    emit_byte(chunk, OpCode::OpPop as usize, line);
+}
+
 fn expression_statement(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
     expression(parser, scanner, chunk);
     parser.consume(TokenType::Semicolon, scanner, "Expect ';' after expression");
@@ -141,15 +626,20 @@ fn named_variable(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk,
         .as_ref()
         .expect("name variable token should not be empty")
         .get_line();
+    let name = parser.previous.as_ref().unwrap().get_lexeme();
     if can_assign && match_token(parser, scanner, TokenType::Equal) {
         expression(parser, scanner, chunk);
         emit_bytes(chunk, OpCode::OpSetGlobal as usize, arg, line);
+        // the assignment has the type of the assigned value
+        let ty = parser.expr_type.clone();
+        parser.assign_global_type(&name, ty, line);
     } else {
         emit_bytes(chunk, OpCode::OpGetGlobal as usize, arg, line);
+        parser.expr_type = parser.global_type(&name);
     }
 }
 
-fn string(previous_token: Option<Token>, chunk: &mut Chunk, can_assign: bool) {
+fn string(previous_token: Option<Token>, chunk: &mut Chunk, _can_assign: bool) {
     let token = previous_token.as_ref().unwrap();
     emit_constant(
         token.get_line(),
@@ -158,7 +648,99 @@ fn string(previous_token: Option<Token>, chunk: &mut Chunk, can_assign: bool) {
     );
 }
 
-fn number(previous_token: Option<Token>, chunk: &mut Chunk, can_assign: bool) {
+fn bytes(parser: &mut Parser, chunk: &mut Chunk, _can_assign: bool) {
+    let token = parser
+        .previous
+        .clone()
+        .expect("bytes token should not be none");
+    match decode_bytes(&token.get_lexeme()) {
+        Ok(value) => emit_constant(token.get_line(), GenericValue::from_bytes(value), chunk),
+        Err(msg) => parser.error_at(&token, &msg),
+    }
+}
+
+fn decode_bytes(lexeme: &str) -> Result<Vec<u8>, String> {
+    // the body of b"...", only ASCII is allowed, other bytes are written as \xNN
+    let mut output = Vec::new();
+    let mut chars = lexeme.chars();
+    while let Some(c) = chars.next() {
+        if !c.is_ascii() {
+            return Err(format!("Non-ASCII character '{}' in bytes literal", c));
+        }
+        if c != '\\' {
+            output.push(c as u8);
+            continue;
+        }
+        let byte = match chars.next() {
+            Some('n') => b'\n',
+            Some('r') => b'\r',
+            Some('t') => b'\t',
+            Some('0') => 0,
+            Some('\\') => b'\\',
+            Some('"') => b'"',
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) if hex.len() == 2 => byte,
+                    _ => return Err(format!("Invalid escape '\\x{}' in bytes literal", hex)),
+                }
+            }
+            Some(c) => return Err(format!("Invalid escape '\\{}' in bytes literal", c)),
+            None => return Err(String::from("Unterminated escape in bytes literal")),
+        };
+        output.push(byte);
+    }
+    Ok(output)
+}
+
+fn interpolation(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk, _can_assign: bool) {
+    /*
+       "a ${x} b ${y} c" arrives as Interpolation("a ") x Interpolation(" b ") y String(" c")
+       and is compiled into the concatenation
+
+       "a " + str(x) + " b " + str(y) + " c"
+    */
+    let token = parser
+        .previous
+        .clone()
+        .expect("interpolation token should not be none");
+    emit_constant(
+        token.get_line(),
+        GenericValue::from_string(token.get_lexeme()),
+        chunk,
+    );
+    loop {
+        expression(parser, scanner, chunk);
+        let line = parser.previous.as_ref().unwrap().get_line();
+        emit_bytes(
+            chunk,
+            OpCode::OpToString as usize,
+            OpCode::OpAdd as usize,
+            line,
+        );
+
+        let has_next = match_token(parser, scanner, TokenType::Interpolation);
+        if !has_next {
+            parser.consume(
+                TokenType::String,
+                scanner,
+                "Expect end of string interpolation",
+            );
+        }
+        let segment = parser.previous.clone().unwrap();
+        emit_constant(
+            segment.get_line(),
+            GenericValue::from_string(segment.get_lexeme()),
+            chunk,
+        );
+        emit_byte(chunk, OpCode::OpAdd as usize, segment.get_line());
+        if !has_next {
+            break;
+        }
+    }
+}
+
+fn number(previous_token: Option<Token>, chunk: &mut Chunk, _can_assign: bool) {
     let token: &Token = previous_token.as_ref().unwrap();
     let num = token
         .get_lexeme()
@@ -172,7 +754,7 @@ fn binary(
     scanner: &mut Scanner,
     previous_token: Option<Token>,
     chunk: &mut Chunk,
-    can_assign: bool,
+    left_type: Type,
 ) {
     let token = previous_token
         .as_ref()
@@ -186,6 +768,17 @@ fn binary(
         chunk,
     );
     let line = token.get_line();
+    let right_type = mem::take(&mut parser.expr_type);
+    parser.expr_type = Type::binary(*op, &left_type, &right_type).unwrap_or_else(|| {
+        let msg = format!(
+            "operator '{}' cannot be applied to {} and {}",
+            op.as_string(),
+            left_type,
+            right_type
+        );
+        parser.type_error(line, &msg);
+        Type::Any
+    });
     match op {
         TokenType::Plus => emit_byte(chunk, OpCode::OpAdd as usize, line),
         TokenType::Minus => emit_byte(chunk, OpCode::OpSubtract as usize, line),
@@ -204,6 +797,10 @@ fn binary(
         TokenType::GreaterEqual => emit_byte(chunk, OpCode::OpGreaterEqual as usize, line),
         TokenType::Less => emit_byte(chunk, OpCode::OpLess as usize, line),
         TokenType::LessEqual => emit_byte(chunk, OpCode::OpLessEqual as usize, line),
+        TokenType::DotDot => emit_bytes(chunk, OpCode::OpRange as usize, 0, line),
+        TokenType::DotDotEqual => emit_bytes(chunk, OpCode::OpRange as usize, 1, line),
+        TokenType::Pipe => emit_byte(chunk, OpCode::OpBitOr as usize, line),
+        TokenType::Ampersand => emit_byte(chunk, OpCode::OpBitAnd as usize, line),
         _ => (), // unreachable
     }
 }
@@ -213,14 +810,23 @@ fn unary(
     scanner: &mut Scanner,
     previous_token: Option<Token>,
     chunk: &mut Chunk,
-    can_assign: bool,
+    _can_assign: bool,
 ) {
     let token = previous_token.as_ref().unwrap();
     let op = token.get_type();
 
-    parse_precedence(parser, scanner, Precedence::PrecUnary, chunk);
     // Compile the operand
-    expression(parser, scanner, chunk);
+    parse_precedence(parser, scanner, Precedence::PrecUnary, chunk);
+    let operand = mem::take(&mut parser.expr_type);
+    parser.expr_type = Type::unary(*op, &operand).unwrap_or_else(|| {
+        let msg = format!(
+            "operator '{}' cannot be applied to {}",
+            op.as_string(),
+            operand
+        );
+        parser.type_error(token.get_line(), &msg);
+        Type::Any
+    });
 
     match op {
         TokenType::Minus => {
@@ -233,7 +839,7 @@ fn unary(
     }
 }
 
-fn literal(previous_token: Option<Token>, chunk: &mut Chunk, can_assign: bool) {
+fn literal(previous_token: Option<Token>, chunk: &mut Chunk, _can_assign: bool) {
     let token = previous_token.as_ref().unwrap();
     match *token.get_type() {
         TokenType::False => emit_byte(chunk, OpCode::OpFalse as usize, token.get_line()),
@@ -243,13 +849,482 @@ fn literal(previous_token: Option<Token>, chunk: &mut Chunk, can_assign: bool) {
     }
 }
 
-fn grouping(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk, can_assign: bool) {
+fn grouping(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk, _can_assign: bool) {
+    // (a) is a grouping, while (), (a,) and (a, b) are tuples
+    let line = parser.previous.as_ref().unwrap().get_line();
+    if match_token(parser, scanner, TokenType::RightParen) {
+        emit_bytes(chunk, OpCode::OpBuildTuple as usize, 0, line);
+        parser.expr_type = Type::Tuple;
+        return;
+    }
     expression(parser, scanner, chunk);
+    if !match_token(parser, scanner, TokenType::Comma) {
+        parser.consume(
+            TokenType::RightParen,
+            scanner,
+            "Expect ')' after expression",
+        );
+        return;
+    }
+    let mut count = 1;
+    while !check(
+        &TokenType::RightParen,
+        parser.current.as_ref().unwrap().get_type(),
+    ) {
+        expression(parser, scanner, chunk);
+        count += 1;
+        if !match_token(parser, scanner, TokenType::Comma) {
+            break;
+        }
+    }
     parser.consume(
         TokenType::RightParen,
         scanner,
-        "Expect ')' after expression",
+        "Expect ')' after tuple elements",
+    );
+    emit_bytes(chunk, OpCode::OpBuildTuple as usize, count, line);
+    parser.expr_type = Type::Tuple;
+}
+
+fn list(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk, _can_assign: bool) {
+    let line = parser.previous.as_ref().unwrap().get_line();
+    if let Some(count) = elements(
+        parser,
+        scanner,
+        chunk,
+        TokenType::RightBracket,
+        "Expect ']' after list elements",
+        0,
+    ) {
+        emit_bytes(chunk, OpCode::OpBuildList as usize, count, line);
+    }
+}
+
+fn set(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk, _can_assign: bool) {
+    // #{1, 2, 3}, where #{} is the empty set
+    let line = parser.previous.as_ref().unwrap().get_line();
+    let mut count = 0;
+    if !check(
+        &TokenType::RightBrace,
+        parser.current.as_ref().unwrap().get_type(),
+    ) {
+        loop {
+            expression(parser, scanner, chunk);
+            count += 1;
+            if !match_token(parser, scanner, TokenType::Comma) {
+                break;
+            }
+        }
+    }
+    parser.consume(
+        TokenType::RightBrace,
+        scanner,
+        "Expect '}' after set elements",
+    );
+    emit_bytes(chunk, OpCode::OpBuildSet as usize, count, line);
+}
+
+fn subscript(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk, can_assign: bool) {
+    // value[index], value[start..end], or buffer[index] = byte
+    let line = parser.previous.as_ref().unwrap().get_line();
+    expression(parser, scanner, chunk);
+    parser.consume(TokenType::RightBracket, scanner, "Expect ']' after index");
+    if can_assign && match_token(parser, scanner, TokenType::Equal) {
+        expression(parser, scanner, chunk);
+        emit_byte(chunk, OpCode::OpSetIndex as usize, line);
+    } else {
+        emit_byte(chunk, OpCode::OpIndex as usize, line);
+    }
+}
+
+fn call(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk, _can_assign: bool) {
+    let line = parser.previous.as_ref().unwrap().get_line();
+    match argument_list(parser, scanner, chunk, 0) {
+        Some(arg_count) => emit_bytes(chunk, OpCode::OpCall as usize, arg_count, line),
+        None => emit_byte(chunk, OpCode::OpCallList as usize, line),
+    }
+}
+
+fn dot(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk, _can_assign: bool) {
+    parser.consume(
+        TokenType::Identifier,
+        scanner,
+        "Expect property name after '.'",
+    );
+    let name = identifier_constant(parser.previous.as_ref(), chunk);
+    let line = parser.previous.as_ref().unwrap().get_line();
+    if match_token(parser, scanner, TokenType::LeftParen) {
+        match argument_list(parser, scanner, chunk, 0) {
+            Some(arg_count) => {
+                emit_bytes(chunk, OpCode::OpInvoke as usize, name, line);
+                emit_byte(chunk, arg_count, line);
+            }
+            None => emit_bytes(chunk, OpCode::OpInvokeList as usize, name, line),
+        }
+    } else {
+        emit_bytes(chunk, OpCode::OpGetProperty as usize, name, line);
+    }
+}
+
+fn optional_chain(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
+    /*
+       a?.b, a?.method() and a?.[i] work like their plain versions unless
+       a is nil, then the whole chain after it is skipped and gives nil:
+
+       a?.b.c()   is   a, OpJumpIfNil end, .b, .c(), end:
+    */
+    let line = parser.previous.as_ref().unwrap().get_line();
+    let jump = emit_jump(chunk, OpCode::OpJumpIfNil, line);
+    parser.optional_jumps.push(jump);
+    if match_token(parser, scanner, TokenType::LeftBracket) {
+        // assigning through an optional chain is not allowed
+        subscript(parser, scanner, chunk, false);
+    } else {
+        dot(parser, scanner, chunk, false);
+    }
+}
+
+fn coalesce(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
+    // a ?? b, b is only evaluated if a is nil
+    let line = parser.previous.as_ref().unwrap().get_line();
+    let else_jump = emit_jump(chunk, OpCode::OpJumpIfNil, line);
+    let end_jump = emit_jump(chunk, OpCode::OpJump, line);
+    patch_jump(chunk, else_jump);
+    emit_byte(chunk, OpCode::OpPop as usize, line);
+    parse_precedence(parser, scanner, Precedence::PrecOr, chunk);
+    patch_jump(chunk, end_jump);
+}
+
+fn pipe(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
+    /*
+       x |> f(y) calls f(x, y), and x |> f is f(x). The piped value is
+       already on the stack when the callee is compiled, OpSwap puts the
+       callee below it so the call sees x as its first argument. The callee
+       can be reached through .name and [index], as in x |> m.f or
+       x |> fs[0], up to the '(' of the argument list.
+    */
+    let line = parser.previous.as_ref().unwrap().get_line();
+    parse_precedence(parser, scanner, Precedence::PrecPrimary, chunk);
+    loop {
+        if match_token(parser, scanner, TokenType::Dot) {
+            parser.consume(
+                TokenType::Identifier,
+                scanner,
+                "Expect property name after '.'",
+            );
+            let name = identifier_constant(parser.previous.as_ref(), chunk);
+            let line = parser.previous.as_ref().unwrap().get_line();
+            emit_bytes(chunk, OpCode::OpGetProperty as usize, name, line);
+        } else if match_token(parser, scanner, TokenType::LeftBracket) {
+            subscript(parser, scanner, chunk, false);
+        } else {
+            break;
+        }
+    }
+    emit_byte(chunk, OpCode::OpSwap as usize, line);
+    let args = if match_token(parser, scanner, TokenType::LeftParen) {
+        argument_list(parser, scanner, chunk, 1)
+    } else {
+        Some(1)
+    };
+    match args {
+        Some(arg_count) => emit_bytes(chunk, OpCode::OpCall as usize, arg_count, line),
+        None => emit_byte(chunk, OpCode::OpCallList as usize, line),
+    }
+}
+
+fn match_expression(
+    parser: &mut Parser,
+    scanner: &mut Scanner,
+    chunk: &mut Chunk,
+    _can_assign: bool,
+) {
+    /*
+       match value {
+           Shape.Rect(w, h) if w == h => w * w,
+           _ => 0,
+       }
+
+       Every arm tests the scrutinee kept on the stack:
+
+               OpMatch         pattern, else next
+               OpDefineGlobal  bindings
+               <guard>
+               OpJumpIfFalse   guard_failed
+               OpPop           guard
+               OpPop           scrutinee
+               <body>
+               OpJump          end
+       guard_failed:
+               OpPop           guard
+       next:   ...
+               OpNoMatch
+       end:
+    */
+    let line = parser.previous.as_ref().unwrap().get_line();
+    expression(parser, scanner, chunk);
+    parser.consume(
+        TokenType::LeftBrace,
+        scanner,
+        "Expect '{' after match value",
+    );
+
+    let mut end_jumps = Vec::new();
+    let mut matched: Vec<String> = Vec::new(); // enums of every variant arm
+    let mut covered: Vec<(String, String)> = Vec::new(); // (enum, variant) of unguarded arms
+    let mut has_catch_all = false;
+    while !check(
+        &TokenType::RightBrace,
+        parser.current.as_ref().unwrap().get_type(),
+    ) && !check(&TokenType::EOF, parser.current.as_ref().unwrap().get_type())
+    {
+        let mut bindings = Vec::new();
+        let pattern = match_pattern(parser, scanner, chunk, &mut bindings);
+        let arm_line = parser.previous.as_ref().unwrap().get_line();
+        if let MatchPattern::Variant { enum_name, .. } = &pattern {
+            matched.push(enum_name.clone());
+        }
+        let pattern_constant = make_constant(GenericValue::from_pattern(pattern.clone()), chunk);
+        emit_bytes(chunk, OpCode::OpMatch as usize, pattern_constant, arm_line);
+        emit_byte(chunk, usize::MAX, arm_line);
+        let next_arm = chunk.count - 1;
+        for binding in bindings.iter().rev() {
+            emit_bytes(chunk, OpCode::OpDefineGlobal as usize, *binding, arm_line);
+            forget_global_type(*binding, parser, chunk);
+        }
+
+        let guard_jump = if match_token(parser, scanner, TokenType::If) {
+            expression(parser, scanner, chunk);
+            let jump = emit_jump(chunk, OpCode::OpJumpIfFalse, arm_line);
+            emit_byte(chunk, OpCode::OpPop as usize, arm_line);
+            Some(jump)
+        } else {
+            match &pattern {
+                p if p.is_irrefutable() => has_catch_all = true,
+                MatchPattern::Variant {
+                    enum_name,
+                    name,
+                    fields,
+                } if fields.iter().all(|f| f.is_irrefutable()) => {
+                    covered.push((enum_name.clone(), name.clone()))
+                }
+                _ => (),
+            }
+            None
+        };
+
+        parser.consume(
+            TokenType::FatArrow,
+            scanner,
+            "Expect '=>' after match pattern",
+        );
+        emit_byte(chunk, OpCode::OpPop as usize, arm_line);
+        expression(parser, scanner, chunk);
+        end_jumps.push(emit_jump(chunk, OpCode::OpJump, arm_line));
+        if let Some(guard_jump) = guard_jump {
+            patch_jump(chunk, guard_jump);
+            emit_byte(chunk, OpCode::OpPop as usize, arm_line);
+        }
+        patch_jump(chunk, next_arm);
+
+        if !match_token(parser, scanner, TokenType::Comma) {
+            break;
+        }
+    }
+    parser.consume(
+        TokenType::RightBrace,
+        scanner,
+        "Expect '}' after match arms",
     );
+    emit_byte(chunk, OpCode::OpNoMatch as usize, line);
+    for jump in end_jumps {
+        patch_jump(chunk, jump);
+    }
+
+    if !has_catch_all {
+        check_exhaustive(parser, matched, &covered, line);
+    }
+}
+
+fn check_exhaustive(
+    parser: &mut Parser,
+    mut enum_names: Vec<String>,
+    covered: &[(String, String)],
+    line: usize,
+) {
+    // warn about the variants of the matched enums that no unguarded arm covers
+    enum_names.sort();
+    enum_names.dedup();
+    for enum_name in enum_names {
+        if let Some(variants) = parser.enums.get(&enum_name) {
+            let missing: Vec<&str> = variants
+                .iter()
+                .filter(|(v, _)| !covered.contains(&(enum_name.clone(), v.clone())))
+                .map(|(v, _)| v.as_str())
+                .collect();
+            if !missing.is_empty() {
+                let msg = format!(
+                    "match on {} is not exhaustive, missing {}",
+                    enum_name,
+                    missing.join(", ")
+                );
+                parser.warning(line, &msg);
+            }
+        }
+    }
+}
+
+fn match_pattern(
+    parser: &mut Parser,
+    scanner: &mut Scanner,
+    chunk: &mut Chunk,
+    bindings: &mut Vec<usize>,
+) -> MatchPattern {
+    // _ | binding | literal | Enum.Variant | Enum.Variant(pattern, ...)
+    parser.advance(scanner);
+    let token = parser.previous.clone().unwrap();
+    match token.get_type() {
+        TokenType::Number => MatchPattern::Literal(GenericValue::from_f64(
+            token.get_lexeme().parse::<f64>().unwrap(),
+        )),
+        TokenType::Minus => {
+            parser.consume(TokenType::Number, scanner, "Expect number after '-'");
+            let number = parser.previous.clone().unwrap();
+            if number.get_type() != &TokenType::Number {
+                // the error is reported, any pattern lets the parser go on
+                return MatchPattern::Wildcard;
+            }
+            MatchPattern::Literal(GenericValue::from_f64(
+                -number.get_lexeme().parse::<f64>().unwrap(),
+            ))
+        }
+        TokenType::String => MatchPattern::Literal(GenericValue::from_string(token.get_lexeme())),
+        TokenType::True => MatchPattern::Literal(GenericValue::from_bool(true)),
+        TokenType::False => MatchPattern::Literal(GenericValue::from_bool(false)),
+        TokenType::Nil => MatchPattern::Literal(GenericValue::from_none()),
+        TokenType::Identifier if token.get_lexeme() == "_" => MatchPattern::Wildcard,
+        TokenType::Identifier if match_token(parser, scanner, TokenType::Dot) => {
+            let enum_name = token.get_lexeme();
+            parser.consume(
+                TokenType::Identifier,
+                scanner,
+                "Expect variant name after '.'",
+            );
+            let variant = parser.previous.clone().unwrap();
+            let mut fields = Vec::new();
+            if match_token(parser, scanner, TokenType::LeftParen) {
+                if !check(
+                    &TokenType::RightParen,
+                    parser.current.as_ref().unwrap().get_type(),
+                ) {
+                    loop {
+                        fields.push(match_pattern(parser, scanner, chunk, bindings));
+                        if !match_token(parser, scanner, TokenType::Comma) {
+                            break;
+                        }
+                    }
+                }
+                parser.consume(
+                    TokenType::RightParen,
+                    scanner,
+                    "Expect ')' after variant patterns",
+                );
+            }
+            let arity = parser.enums.get(&enum_name).map(|variants| {
+                variants
+                    .iter()
+                    .find(|(name, _)| *name == variant.get_lexeme())
+                    .map(|(_, arity)| *arity)
+            });
+            match arity {
+                Some(None) => parser.error_at(&variant, "Unknown enum variant"),
+                Some(Some(arity)) if arity != fields.len() => {
+                    parser.error_at(&variant, "Wrong number of fields in variant pattern")
+                }
+                _ => (),
+            }
+            MatchPattern::Variant {
+                enum_name,
+                name: variant.get_lexeme(),
+                fields,
+            }
+        }
+        TokenType::Identifier => {
+            bindings.push(identifier_constant(Some(&token), chunk));
+            MatchPattern::Binding
+        }
+        _ => {
+            parser.error_at(&token, "Expect pattern");
+            MatchPattern::Wildcard
+        }
+    }
+}
+
+fn argument_list(
+    parser: &mut Parser,
+    scanner: &mut Scanner,
+    chunk: &mut Chunk,
+    before: usize,
+) -> Option<usize> {
+    elements(
+        parser,
+        scanner,
+        chunk,
+        TokenType::RightParen,
+        "Expect ')' after arguments",
+        before,
+    )
+}
+
+fn elements(
+    parser: &mut Parser,
+    scanner: &mut Scanner,
+    chunk: &mut Chunk,
+    closing: TokenType,
+    msg: &str,
+    before: usize,
+) -> Option<usize> {
+    /*
+       The comma separated elements of a list literal or an argument list.
+       Without a spread they stay on the stack and their count, including
+       the `before` values that were already pushed, is returned.
+
+       [a, ...b, c]   is   a, OpBuildList 1, b, OpListExtend, c, OpListAppend
+
+       After the first `...` every element goes into one list instead, whose
+       length is only known at runtime, and None is returned.
+    */
+    let mut count = before;
+    let mut spread = false;
+    if !check(&closing, parser.current.as_ref().unwrap().get_type()) {
+        loop {
+            if match_token(parser, scanner, TokenType::DotDotDot) {
+                let line = parser.previous.as_ref().unwrap().get_line();
+                if !spread {
+                    emit_bytes(chunk, OpCode::OpBuildList as usize, count, line);
+                    spread = true;
+                }
+                expression(parser, scanner, chunk);
+                emit_byte(chunk, OpCode::OpListExtend as usize, line);
+            } else {
+                expression(parser, scanner, chunk);
+                if spread {
+                    let line = parser.previous.as_ref().unwrap().get_line();
+                    emit_byte(chunk, OpCode::OpListAppend as usize, line);
+                }
+                count += 1;
+            }
+            if !match_token(parser, scanner, TokenType::Comma) {
+                break;
+            }
+        }
+    }
+    parser.consume(closing, scanner, msg);
+    if spread {
+        None
+    } else {
+        Some(count)
+    }
 }
 
 pub fn expression(parser: &mut Parser, scanner: &mut Scanner, chunk: &mut Chunk) {
@@ -272,17 +1347,21 @@ fn parse_precedence(
     let rule = ParseRule::get_rule(*previous_type);
 
     if rule.prefix == ParseFn::Null {
-        error(token.get_line(), "Expect expression");
+        parser.error_at(&token, "Expect expression");
         return;
     }
     // this is prefixRule() in the book, since I'm not sure how to store function pointers at this moment
     let can_assign = precedence as usize <= Precedence::PrecAssignment as usize;
+    let chain_start = parser.optional_jumps.len();
     execute_parsfn(parser, rule.prefix, scanner, chunk, can_assign);
 
     loop {
         let curr_token = parser.current.as_mut().unwrap();
         let rule = ParseRule::get_rule(*curr_token.get_type());
         if precedence as usize <= rule.precedence as usize {
+            if (rule.precedence as usize) < Precedence::PrecCall as usize {
+                end_optional_chain(parser, chunk, chain_start);
+            }
             parser.advance(scanner);
 
             let infix_rule = ParseRule::get_rule(
@@ -299,6 +1378,14 @@ fn parse_precedence(
             break;
         }
     }
+    end_optional_chain(parser, chunk, chain_start);
+}
+
+fn end_optional_chain(parser: &mut Parser, chunk: &mut Chunk, chain_start: usize) {
+    // a nil receiver skips the rest of the chain and becomes its value
+    for offset in parser.optional_jumps.split_off(chain_start) {
+        patch_jump(chunk, offset);
+    }
 }
 
 fn execute_parsfn(
@@ -309,16 +1396,46 @@ fn execute_parsfn(
     can_assign: bool,
 ) {
     let token: Option<Token> = parser.previous.clone(); // don't like this
+
+    // for an infix rule, the type of the left operand
+    let left_type = mem::take(&mut parser.expr_type);
     match parsfn {
-        ParseFn::Literal => literal(token, chunk, can_assign),
-        ParseFn::Number => number(token, chunk, can_assign),
-        ParseFn::Unary => unary(parser, scanner, token, chunk, can_assign),
-        ParseFn::Binary => binary(parser, scanner, token, chunk, can_assign),
+        ParseFn::Literal => literal(token.clone(), chunk, can_assign),
+        ParseFn::Number => number(token.clone(), chunk, can_assign),
+        ParseFn::Unary => unary(parser, scanner, token.clone(), chunk, can_assign),
+        ParseFn::Binary => binary(parser, scanner, token.clone(), chunk, left_type),
         ParseFn::Grouping => grouping(parser, scanner, chunk, can_assign),
-        ParseFn::String => string(token, chunk, can_assign),
+        ParseFn::String => string(token.clone(), chunk, can_assign),
         ParseFn::Variable => variable(parser, scanner, chunk, can_assign),
+        ParseFn::Interpolation => interpolation(parser, scanner, chunk, can_assign),
+        ParseFn::List => list(parser, scanner, chunk, can_assign),
+        ParseFn::Dot => dot(parser, scanner, chunk, can_assign),
+        ParseFn::Match => match_expression(parser, scanner, chunk, can_assign),
+        ParseFn::Set => set(parser, scanner, chunk, can_assign),
+        ParseFn::Call => call(parser, scanner, chunk, can_assign),
+        ParseFn::Bytes => bytes(parser, chunk, can_assign),
+        ParseFn::Subscript => subscript(parser, scanner, chunk, can_assign),
+        ParseFn::OptionalChain => optional_chain(parser, scanner, chunk),
+        ParseFn::Coalesce => coalesce(parser, scanner, chunk),
+        ParseFn::Pipe => pipe(parser, scanner, chunk),
         ParseFn::Null => (),
     }
+    parser.expr_type = match parsfn {
+        ParseFn::Number => Type::number_literal(&token.unwrap().get_lexeme()),
+        ParseFn::Literal => match token.unwrap().get_type() {
+            TokenType::True | TokenType::False => Type::Bool,
+            _ => Type::Nil,
+        },
+        ParseFn::String | ParseFn::Interpolation => Type::Str,
+        ParseFn::Bytes => Type::Bytes,
+        ParseFn::List => Type::List,
+        ParseFn::Set => Type::Set,
+        // these work out their type themselves
+        ParseFn::Unary | ParseFn::Binary | ParseFn::Grouping | ParseFn::Variable => {
+            mem::take(&mut parser.expr_type)
+        }
+        _ => Type::Any,
+    };
 }
 
 fn emit_byte(chunk: &mut Chunk, byte: usize, previous_line: usize) {
@@ -330,6 +1447,23 @@ fn emit_bytes(chunk: &mut Chunk, byte1: usize, byte2: usize, previous_line: usiz
     emit_byte(chunk, byte2, previous_line);
 }
 
+fn emit_jump(chunk: &mut Chunk, op: OpCode, line: usize) -> usize {
+    // the operand is patched once the jump target is known
+    emit_bytes(chunk, op as usize, usize::MAX, line);
+    chunk.count - 1
+}
+
+fn patch_jump(chunk: &mut Chunk, offset: usize) {
+    let jump = chunk.count - offset - 1;
+    chunk.bytecode[offset] = jump;
+}
+
+fn emit_loop(chunk: &mut Chunk, loop_start: usize, line: usize) {
+    emit_byte(chunk, OpCode::OpLoop as usize, line);
+    let offset = chunk.count - loop_start + 1;
+    emit_byte(chunk, offset, line);
+}
+
 fn end_compiler(chunk: &mut Chunk, previous_line: usize) {
     #[cfg(debug_assertions)]
     {
@@ -1,9 +1,10 @@
 #[cfg(test)]
 mod test {
     use crate::chunk::Chunk;
-    use crate::compiler::{declaration, expression};
+    use crate::compiler::{compile, compile_with_types, declaration, expression};
     use crate::parser::Parser;
     use crate::scanner::Scanner;
+    use crate::tokens::TokenType;
     use crate::vm::disassemble_chunk;
 
     // #[test]
@@ -35,4 +36,148 @@ mod test {
         declaration(&mut parser, &mut scanner, &mut chunk);
         disassemble_chunk(&chunk, "scan string");
     }
+
+    fn check_types(s: &str) -> bool {
+        let mut chunk = Chunk::default();
+        compile_with_types(s.to_string(), &mut chunk)
+    }
+
+    #[test]
+    fn type_annotations() {
+        assert!(check_types(
+            r#"var x: Int = 1; var y: Num = x / 2; var s: Str? = nil; s = "a"; var b: Bool = x < y;"#
+        ));
+        assert!(!check_types("var x: Int = 1; x = \"a\";"));
+        assert!(!check_types("var x: Int = 1.5;"));
+        assert!(!check_types("var s: Str;"));
+        assert!(!check_types("var n: Num? = 1; var m = n + 1;"));
+    }
+
+    #[test]
+    fn inferred_types() {
+        assert!(!check_types(r#"var n = 1; var s: Str = n * 2;"#));
+        assert!(!check_types(r#"var t = (1, 2); var l: List = t;"#));
+        // a variable assigned values of different types is no longer checked
+        assert!(check_types(r#"var n = 1; n = "a"; var s: Str = n;"#));
+        assert!(check_types(
+            r#"var n = "a"; for (n in [1]) { var m: Int = n; }"#
+        ));
+    }
+
+    #[test]
+    fn operator_types() {
+        assert!(!check_types(r#"var x = 1 + "a";"#));
+        assert!(!check_types("var x = -true;"));
+        assert!(!check_types(r#"var x = 1 < "a";"#));
+        assert!(check_types(
+            r#"var x = #{1} | #{2}; var y: Set = x - #{1};"#
+        ));
+        // values the checker can't work out are never reported
+        assert!(check_types(r#"var x = [1][0] + "a";"#));
+    }
+
+    #[test]
+    fn types_only_checked_on_request() {
+        let mut chunk = Chunk::default();
+        assert!(compile("var x: Int = \"a\";".to_string(), &mut chunk));
+        // an unknown type name is erased like any other annotation
+        let mut chunk = Chunk::default();
+        assert!(compile("var x: Integer = 1;".to_string(), &mut chunk));
+        assert!(!check_types("var x: Integer = 1;"));
+        assert!(check_types(
+            "enum Shape { Empty } var s: Shape = Shape.Empty;"
+        ));
+        assert!(check_types(
+            "record Point(x, y) var p: Point = Point(1, 2);"
+        ));
+    }
+
+    #[test]
+    fn imported_type_names() {
+        let source = r#"from "m.lo" import Point; var p: Point = Point(1, 2);"#;
+        let mut chunk = Chunk::default();
+        assert!(compile(source.to_string(), &mut chunk));
+        assert!(check_types(source));
+    }
+
+    #[test]
+    fn loop_variable_types() {
+        assert!(!check_types(r#"var i: Int = 0; for (i in "ab") print i;"#));
+        assert!(check_types(r#"var s: Str = ""; for (s in "ab") print s;"#));
+        assert!(check_types(r#"var i: Int = 0; for (i in b"ab") print i;"#));
+        // a new definition drops the annotation
+        assert!(check_types(
+            r#"var i: Int = 0; for (var i in "ab") print i;"#
+        ));
+        assert!(check_types(
+            r#"var i: Int = 0; for (i in [1]) print i; i = 1;"#
+        ));
+        assert!(!check_types(
+            r#"var i: Int = 0; for (i in [1]) print i; i = "a";"#
+        ));
+    }
+
+    #[test]
+    fn negative_pattern_needs_number() {
+        let mut chunk = Chunk::default();
+        assert!(!compile(
+            "var r = match 1 { -x => 1, _ => 2 };".to_string(),
+            &mut chunk
+        ));
+        let mut chunk = Chunk::default();
+        assert!(!compile(
+            "var r = match 1 { - => 1 };".to_string(),
+            &mut chunk
+        ));
+    }
+
+    fn warnings(s: &str) -> usize {
+        let mut scanner = Scanner::new(s.to_string());
+        let mut chunk = Chunk::default();
+        let mut parser = Parser::new();
+        parser.advance(&mut scanner);
+        while parser.current.as_ref().unwrap().get_type() != &TokenType::EOF {
+            declaration(&mut parser, &mut scanner, &mut chunk);
+        }
+        assert!(!parser.had_error, "compile error");
+        parser.warnings
+    }
+
+    #[test]
+    fn match_exhaustiveness_warnings() {
+        let shape = "enum S { A, B } var s = S.A;";
+        assert_eq!(
+            warnings(&format!(
+                "{} var r = match s {{ S.A => 1, S.B => 2 }};",
+                shape
+            )),
+            0
+        );
+        assert_eq!(
+            warnings(&format!("{} var r = match s {{ S.A => 1 }};", shape)),
+            1
+        );
+        // a guarded arm may not match, so it covers nothing
+        assert_eq!(
+            warnings(&format!(
+                "{} var r = match s {{ S.A if true => 1 }};",
+                shape
+            )),
+            1
+        );
+        assert_eq!(
+            warnings(&format!(
+                "{} var r = match s {{ S.A if true => 1, S.B => 2 }};",
+                shape
+            )),
+            1
+        );
+        assert_eq!(
+            warnings(&format!(
+                "{} var r = match s {{ S.A if true => 1, _ => 2 }};",
+                shape
+            )),
+            0
+        );
+    }
 }
@@ -1 +1,2 @@
 pub const STACK_MAX: u8 = u8::MAX; // 65532
+pub const STRING_MAX: usize = 1 << 30; // longest string a method may build, in bytes
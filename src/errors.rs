@@ -9,3 +9,7 @@ pub fn report(line: usize, wh: String, msg: &str) {
 pub fn runtime_error(line: usize, msg: &str) {
     println!("line[ {} ] Runtime Error : {}", line, msg)
 }
+
+pub fn warning(line: usize, msg: &str) {
+    println!("line[ {} ] Warning : {}", line, msg)
+}
@@ -3,12 +3,19 @@ pub mod compiler;
 mod compiler_test;
 pub mod constants;
 pub mod errors;
+pub mod methods;
+mod methods_test;
+pub mod modules;
+pub mod natives;
 pub mod parser;
 mod parser_test;
 pub mod rules;
 pub mod scanner;
 mod scanner_test;
+pub mod set;
 pub mod table;
 pub mod tokens;
+pub mod types;
 pub mod values;
 pub mod vm;
+mod vm_test;
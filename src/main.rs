@@ -1,7 +1,7 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use core::panic;
 use lolang::chunk::Chunk;
-use lolang::compiler::compile;
+use lolang::compiler::{compile, compile_with_types};
 use lolang::vm::{InterpretResult, VirtualMachine};
 use std::fs::File;
 use std::io::{stdout, Read, Write};
@@ -12,10 +12,24 @@ use std::process::exit;
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(short, long, value_delimiter = ' ', num_args=1..)]
     path: Vec<PathBuf>,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compile files without running them
+    Check {
+        /// Also report values that don't match their type annotations
+        #[arg(long)]
+        types: bool,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
 fn trim_end(s: &mut String) {
     if s.ends_with('\n') {
         s.pop();
@@ -65,15 +79,21 @@ fn run_prompt() {
     }
 }
 
-fn run_file(path: &PathBuf) {
-    let mut vm = VirtualMachine::default();
-    let mut chunk = Chunk::default();
+fn read_file(path: &PathBuf) -> String {
     let mut contents = String::new();
     if let Ok(mut file) = File::open(path) {
         let _ = file.read_to_string(&mut contents);
     } else {
         panic!("Couldn't open file or file doesn't not exist")
     }
+    contents
+}
+
+fn run_file(path: &PathBuf) {
+    let mut vm = VirtualMachine::default();
+    vm.modules.enter(path);
+    let mut chunk = Chunk::default();
+    let contents = read_file(path);
     match interpret(contents, &mut vm, &mut chunk) {
         InterpretResult::InterpretOk => (),
         InterpretResult::InterpretCompileError => exit(65),
@@ -81,15 +101,38 @@ fn run_file(path: &PathBuf) {
     }
 }
 
+fn check_files(files: &[PathBuf], types: bool) {
+    // every file is checked so all the errors are reported at once
+    let mut ok = true;
+    for path in files {
+        let mut chunk = Chunk::default();
+        let contents = read_file(path);
+        let compiled = if types {
+            compile_with_types(contents, &mut chunk)
+        } else {
+            compile(contents, &mut chunk)
+        };
+        if !compiled {
+            println!("{}: check failed", path.display());
+            ok = false;
+        }
+    }
+    if !ok {
+        exit(65)
+    }
+}
+
 fn main() {
     let args = Args::parse();
 
-    if args.path.is_empty() {
+    if let Some(Command::Check { types, files }) = &args.command {
+        check_files(files, *types);
+    } else if args.path.is_empty() {
         run_prompt();
-    } else if args.path.len() == 1 {
-        let path = &args.path[0];
-        run_file(path);
     } else {
-        panic!("Multiple file parsing not supported yet");
+        // every file is a separate script, run one after the other
+        for path in &args.path {
+            run_file(path);
+        }
     }
 }
@@ -0,0 +1,399 @@
+use std::cell::RefCell;
+use std::fmt::Write;
+
+use crate::constants;
+use crate::set::ValueSet;
+use crate::values::{ByteBuffer, Enum, GenericValue, Record, Variant};
+use crate::vm::RuntimeError;
+
+/*
+   Built-in methods, dispatched on the type of the receiver by OpInvoke.
+
+   Strings are indexed by code points, not by bytes, so for "héllo"
+   len() is 5 and substring(1, 2) is "é".
+*/
+pub fn invoke(
+    receiver: &GenericValue,
+    name: &str,
+    args: &[GenericValue],
+) -> Result<GenericValue, RuntimeError> {
+    if let Some(s) = receiver.as_string() {
+        return string_method(&s, name, args);
+    }
+    if let Some(set) = receiver.as_set() {
+        return set_method(&set, name, args);
+    }
+    if let Some(b) = receiver.as_buffer() {
+        return buffer_method(&b, name, args);
+    }
+    if let Some(b) = receiver.as_bytes() {
+        return bytes_method(&b, name, args);
+    }
+    if let Some(e) = receiver.as_enum() {
+        return construct_variant(e, name, args);
+    }
+    if let Some(m) = receiver.as_module() {
+        // `m.Point(1, 2)` calls a member that is callable, a record type the
+        // module declared or a native it bound to a name, such as `var f = str;`
+        if let Some(member) = m.globals.get(name) {
+            return call(member, args);
+        }
+    }
+    Err(RuntimeError::UndefinedMethod(
+        receiver.get_type_as_str(),
+        name.to_string(),
+    ))
+}
+
+pub fn call(callee: &GenericValue, args: &[GenericValue]) -> Result<GenericValue, RuntimeError> {
+    // `callee(args)`, a native function or a record constructor
+    if let Some(native) = callee.as_native() {
+        return (native.function)(args);
+    }
+    if let Some(r) = callee.as_record_type() {
+        check_arity(&r.name, args, r.fields.len())?;
+        return Ok(GenericValue::from_record(Record {
+            name: r.name.clone(),
+            fields: r.fields.clone(),
+            values: args.to_vec(),
+        }));
+    }
+    Err(RuntimeError::InvalidOperation(format!(
+        "can only call functions, not {}",
+        callee.get_type_as_str()
+    )))
+}
+
+pub fn get_property(receiver: &GenericValue, name: &str) -> Result<GenericValue, RuntimeError> {
+    // `receiver.name` without a call, e.g. the unit variant Shape.Empty or a
+    // global of an imported module
+    if let Some(e) = receiver.as_enum() {
+        if let Some(variant) = e.variants.iter().find(|v| v.name == name) {
+            if !variant.fields.is_empty() {
+                return Err(RuntimeError::InvalidOperation(format!(
+                    "variant {}.{} expects {} fields, construct it with {}.{}(...)",
+                    e.name,
+                    name,
+                    variant.fields.len(),
+                    e.name,
+                    name
+                )));
+            }
+            return construct_variant(e, name, &[]);
+        }
+    }
+    if let Some(m) = receiver.as_module() {
+        if let Some(member) = m.globals.get(name) {
+            return Ok(member.clone());
+        }
+    }
+    if let Some(r) = receiver.as_record() {
+        if let Some(i) = r.fields.iter().position(|f| f == name) {
+            return Ok(r.values[i].clone());
+        }
+    }
+    Err(RuntimeError::UndefinedProperty(
+        receiver.get_type_as_str(),
+        name.to_string(),
+    ))
+}
+
+fn construct_variant(
+    e: &Enum,
+    name: &str,
+    args: &[GenericValue],
+) -> Result<GenericValue, RuntimeError> {
+    // Shape.Rect(2, 3)
+    let variant = e
+        .variants
+        .iter()
+        .find(|v| v.name == name)
+        .ok_or_else(|| RuntimeError::UndefinedProperty(e.name.clone(), name.to_string()))?;
+    check_arity(name, args, variant.fields.len())?;
+    Ok(GenericValue::from_variant(Variant {
+        enum_name: e.name.clone(),
+        name: name.to_string(),
+        fields: args.to_vec(),
+    }))
+}
+
+fn string_method(s: &str, name: &str, args: &[GenericValue]) -> Result<GenericValue, RuntimeError> {
+    match name {
+        "len" => {
+            check_arity(name, args, 0)?;
+            Ok(GenericValue::from_f64(s.chars().count() as f64))
+        }
+        "chars" => {
+            check_arity(name, args, 0)?;
+            Ok(GenericValue::from_list(
+                s.chars()
+                    .map(|c| GenericValue::from_string(c.to_string()))
+                    .collect(),
+            ))
+        }
+        "bytes" => {
+            check_arity(name, args, 0)?;
+            Ok(GenericValue::from_list(
+                s.bytes()
+                    .map(|b| GenericValue::from_f64(b as f64))
+                    .collect(),
+            ))
+        }
+        "substring" => {
+            // substring(start) or substring(start, end), end is exclusive
+            if args.is_empty() || args.len() > 2 {
+                return Err(arity_error(name, "1 or 2", args.len()));
+            }
+            let len = s.chars().count();
+            let start = index_arg(name, &args[0])?;
+            let end = match args.get(1) {
+                Some(arg) => index_arg(name, arg)?,
+                None => len,
+            };
+            if start > end || end > len {
+                return Err(RuntimeError::InvalidOperation(format!(
+                    "substring({}, {}) out of range for string of length {}",
+                    start, end, len
+                )));
+            }
+            Ok(GenericValue::from_string(
+                s.chars().skip(start).take(end - start).collect(),
+            ))
+        }
+        "find" => {
+            // code point index of the first occurrence, nil if there is none
+            check_arity(name, args, 1)?;
+            let needle = string_arg(name, &args[0])?;
+            Ok(match s.find(&needle) {
+                Some(byte_index) => GenericValue::from_f64(s[..byte_index].chars().count() as f64),
+                None => GenericValue::from_none(),
+            })
+        }
+        "replace" => {
+            check_arity(name, args, 2)?;
+            let from = string_arg(name, &args[0])?;
+            let to = string_arg(name, &args[1])?;
+            if from.is_empty() {
+                return Err(RuntimeError::InvalidOperation(
+                    "replace() pattern must not be empty".to_string(),
+                ));
+            }
+            Ok(GenericValue::from_string(s.replace(&from, &to)))
+        }
+        "split" => {
+            check_arity(name, args, 1)?;
+            let separator = string_arg(name, &args[0])?;
+            if separator.is_empty() {
+                return Err(RuntimeError::InvalidOperation(
+                    "split() separator must not be empty".to_string(),
+                ));
+            }
+            Ok(GenericValue::from_list(
+                s.split(&separator)
+                    .map(|part| GenericValue::from_string(part.to_string()))
+                    .collect(),
+            ))
+        }
+        "join" => {
+            // the receiver is the separator: ", ".join(["a", "b"])
+            check_arity(name, args, 1)?;
+            let values = args[0].as_list().ok_or_else(|| {
+                RuntimeError::InvalidOperation(format!(
+                    "join() expects a list but got {}",
+                    args[0].get_type_as_str()
+                ))
+            })?;
+            let parts: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            Ok(GenericValue::from_string(parts.join(s)))
+        }
+        "trim" => {
+            check_arity(name, args, 0)?;
+            Ok(GenericValue::from_string(s.trim().to_string()))
+        }
+        "upper" => {
+            check_arity(name, args, 0)?;
+            Ok(GenericValue::from_string(s.to_uppercase()))
+        }
+        "lower" => {
+            check_arity(name, args, 0)?;
+            Ok(GenericValue::from_string(s.to_lowercase()))
+        }
+        "starts_with" => {
+            check_arity(name, args, 1)?;
+            let prefix = string_arg(name, &args[0])?;
+            Ok(GenericValue::from_bool(s.starts_with(&prefix)))
+        }
+        "ends_with" => {
+            check_arity(name, args, 1)?;
+            let suffix = string_arg(name, &args[0])?;
+            Ok(GenericValue::from_bool(s.ends_with(&suffix)))
+        }
+        "repeat" => {
+            check_arity(name, args, 1)?;
+            let n = index_arg(name, &args[0])?;
+            match s.len().checked_mul(n) {
+                Some(len) if len <= constants::STRING_MAX => {
+                    Ok(GenericValue::from_string(s.repeat(n)))
+                }
+                _ => Err(RuntimeError::InvalidOperation(format!(
+                    "repeat() result would be longer than {} bytes",
+                    constants::STRING_MAX
+                ))),
+            }
+        }
+        "encode" => {
+            // the UTF-8 bytes of the string
+            check_arity(name, args, 0)?;
+            Ok(GenericValue::from_bytes(s.as_bytes().to_vec()))
+        }
+        _ => Err(RuntimeError::UndefinedMethod(
+            String::from("string"),
+            name.to_string(),
+        )),
+    }
+}
+
+fn set_method(
+    set: &RefCell<ValueSet>,
+    name: &str,
+    args: &[GenericValue],
+) -> Result<GenericValue, RuntimeError> {
+    /*
+       add and remove change the set in place and report whether they did
+       anything. The argument is checked before the set is borrowed mutably,
+       since checking or hashing a set borrows it, and it may be this one.
+    */
+    match name {
+        "add" => {
+            check_arity(name, args, 1)?;
+            args[0].check_hashable()?;
+            let added = set.borrow_mut().add(args[0].clone())?;
+            Ok(GenericValue::from_bool(added))
+        }
+        "remove" => {
+            check_arity(name, args, 1)?;
+            if args[0].check_hashable().is_err() {
+                // never in a set, but a frozen set still can't be changed
+                set.borrow().check_mutable()?;
+                return Ok(GenericValue::from_bool(false));
+            }
+            Ok(GenericValue::from_bool(set.borrow_mut().remove(&args[0])?))
+        }
+        "contains" => {
+            check_arity(name, args, 1)?;
+            Ok(GenericValue::from_bool(set.borrow().contains(&args[0])))
+        }
+        "len" => {
+            check_arity(name, args, 0)?;
+            Ok(GenericValue::from_f64(set.borrow().len() as f64))
+        }
+        _ => Err(RuntimeError::UndefinedMethod(
+            String::from("set"),
+            name.to_string(),
+        )),
+    }
+}
+
+fn bytes_method(b: &[u8], name: &str, args: &[GenericValue]) -> Result<GenericValue, RuntimeError> {
+    // shared by bytes and buffers
+    match name {
+        "len" => {
+            check_arity(name, args, 0)?;
+            Ok(GenericValue::from_f64(b.len() as f64))
+        }
+        "hex" => {
+            check_arity(name, args, 0)?;
+            let mut hex = String::with_capacity(b.len() * 2);
+            for byte in b {
+                let _ = write!(hex, "{:02x}", byte);
+            }
+            Ok(GenericValue::from_string(hex))
+        }
+        "utf8" => {
+            check_arity(name, args, 0)?;
+            match String::from_utf8(b.to_vec()) {
+                Ok(s) => Ok(GenericValue::from_string(s)),
+                Err(e) => Err(RuntimeError::InvalidOperation(format!(
+                    "invalid UTF-8 at byte {}",
+                    e.utf8_error().valid_up_to()
+                ))),
+            }
+        }
+        _ => Err(RuntimeError::UndefinedMethod(
+            String::from("bytes"),
+            name.to_string(),
+        )),
+    }
+}
+
+fn buffer_method(
+    b: &RefCell<ByteBuffer>,
+    name: &str,
+    args: &[GenericValue],
+) -> Result<GenericValue, RuntimeError> {
+    match name {
+        "push" => {
+            check_arity(name, args, 1)?;
+            let byte = args[0].as_byte()?;
+            b.borrow_mut().bytes_mut()?.push(byte);
+            Ok(GenericValue::from_none())
+        }
+        "extend" => {
+            check_arity(name, args, 1)?;
+            let bytes = args[0].as_bytes().ok_or_else(|| {
+                RuntimeError::InvalidOperation(format!(
+                    "extend() expects bytes but got {}",
+                    args[0].get_type_as_str()
+                ))
+            })?;
+            b.borrow_mut().bytes_mut()?.extend(bytes);
+            Ok(GenericValue::from_none())
+        }
+        "to_bytes" => {
+            // an immutable copy of the current contents
+            check_arity(name, args, 0)?;
+            Ok(GenericValue::from_bytes(b.borrow().bytes.clone()))
+        }
+        _ => match bytes_method(&b.borrow().bytes, name, args) {
+            Err(RuntimeError::UndefinedMethod(_, name)) => {
+                Err(RuntimeError::UndefinedMethod(String::from("buffer"), name))
+            }
+            result => result,
+        },
+    }
+}
+
+fn check_arity(name: &str, args: &[GenericValue], expect: usize) -> Result<(), RuntimeError> {
+    if args.len() != expect {
+        return Err(arity_error(name, &expect.to_string(), args.len()));
+    }
+    Ok(())
+}
+
+fn arity_error(name: &str, expect: &str, got: usize) -> RuntimeError {
+    RuntimeError::InvalidOperation(format!(
+        "{}() expects {} arguments but got {}",
+        name, expect, got
+    ))
+}
+
+fn string_arg(name: &str, arg: &GenericValue) -> Result<String, RuntimeError> {
+    arg.as_string().ok_or_else(|| {
+        RuntimeError::InvalidOperation(format!(
+            "{}() expects a string but got {}",
+            name,
+            arg.get_type_as_str()
+        ))
+    })
+}
+
+fn index_arg(name: &str, arg: &GenericValue) -> Result<usize, RuntimeError> {
+    match arg.as_number() {
+        Some(n) if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+        _ => Err(RuntimeError::InvalidOperation(format!(
+            "{}() expects a non-negative integer but got {}",
+            name,
+            arg.repr()
+        ))),
+    }
+}
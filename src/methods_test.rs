@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod test {
+    use crate::methods::invoke;
+    use crate::values::GenericValue;
+
+    fn string(s: &str) -> GenericValue {
+        GenericValue::from_string(s.to_string())
+    }
+
+    fn number(n: f64) -> GenericValue {
+        GenericValue::from_f64(n)
+    }
+
+    fn call(receiver: &str, name: &str, args: &[GenericValue]) -> GenericValue {
+        invoke(&string(receiver), name, args).unwrap()
+    }
+
+    #[test]
+    fn string_len_counts_code_points() {
+        assert_eq!(call("héllo", "len", &[]), number(5.0));
+        assert_eq!(call("你好", "len", &[]), number(2.0));
+        assert_eq!(call("", "len", &[]), number(0.0));
+    }
+
+    #[test]
+    fn string_chars_and_bytes() {
+        assert_eq!(
+            call("é!", "chars", &[]),
+            GenericValue::from_list(vec![string("é"), string("!")])
+        );
+        assert_eq!(
+            call("é", "bytes", &[]),
+            GenericValue::from_list(vec![number(195.0), number(169.0)])
+        );
+    }
+
+    #[test]
+    fn string_substring() {
+        assert_eq!(call("héllo", "substring", &[number(1.0)]), string("éllo"));
+        assert_eq!(
+            call("你好世界", "substring", &[number(1.0), number(3.0)]),
+            string("好世")
+        );
+        assert_eq!(
+            call("abc", "substring", &[number(3.0), number(3.0)]),
+            string("")
+        );
+        assert!(invoke(&string("abc"), "substring", &[number(2.0), number(1.0)]).is_err());
+        assert!(invoke(&string("abc"), "substring", &[number(0.0), number(4.0)]).is_err());
+        assert!(invoke(&string("abc"), "substring", &[number(0.5)]).is_err());
+        assert!(invoke(&string("abc"), "substring", &[]).is_err());
+    }
+
+    #[test]
+    fn string_find() {
+        assert_eq!(call("héllo", "find", &[string("l")]), number(2.0));
+        assert_eq!(
+            call("héllo", "find", &[string("x")]),
+            GenericValue::from_none()
+        );
+        assert!(invoke(&string("abc"), "find", &[number(1.0)]).is_err());
+    }
+
+    #[test]
+    fn string_replace_split_join() {
+        assert_eq!(
+            call("a-b-c", "replace", &[string("-"), string("+")]),
+            string("a+b+c")
+        );
+        let parts = call("a,b,,c", "split", &[string(",")]);
+        assert_eq!(
+            parts,
+            GenericValue::from_list(vec![string("a"), string("b"), string(""), string("c")])
+        );
+        assert_eq!(call("-", "join", &[parts]), string("a-b--c"));
+        assert_eq!(
+            call(
+                ", ",
+                "join",
+                &[GenericValue::from_list(vec![number(1.0), string("x")])]
+            ),
+            string("1, x")
+        );
+        assert!(invoke(&string("abc"), "split", &[string("")]).is_err());
+        assert!(invoke(&string(","), "join", &[string("abc")]).is_err());
+    }
+
+    #[test]
+    fn string_case_and_trim() {
+        assert_eq!(call("  ab \n", "trim", &[]), string("ab"));
+        assert_eq!(call("straße", "upper", &[]), string("STRASSE"));
+        assert_eq!(call("ÀB", "lower", &[]), string("àb"));
+    }
+
+    #[test]
+    fn string_affixes_and_repeat() {
+        assert_eq!(
+            call("lolang", "starts_with", &[string("lo")]),
+            GenericValue::from_bool(true)
+        );
+        assert_eq!(
+            call("lolang", "ends_with", &[string("lo")]),
+            GenericValue::from_bool(false)
+        );
+        assert_eq!(call("ab", "repeat", &[number(3.0)]), string("ababab"));
+        assert!(invoke(&string("ab"), "repeat", &[number(-1.0)]).is_err());
+        assert!(invoke(&string("ab"), "repeat", &[number(1e20)]).is_err());
+        assert!(invoke(&string("ab"), "repeat", &[number(2f64.powi(30))]).is_err());
+        assert_eq!(call("", "repeat", &[number(1e20)]), string(""));
+    }
+
+    #[test]
+    fn undefined_method() {
+        assert!(invoke(&string("abc"), "reverse", &[]).is_err());
+        assert!(invoke(&number(1.0), "len", &[]).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use crate::chunk::Chunk;
+use crate::compiler::compile;
+use crate::values::{GenericValue, Module};
+use crate::vm::{InterpretResult, RuntimeError, VirtualMachine};
+
+/*
+   The files a virtual machine has imported. Every file is compiled and run
+   once in its own virtual machine, later imports of the same file share the
+   module built from its globals.
+
+   `loading` is the chain of files currently being run, the script first and
+   the innermost import last. Relative paths are resolved against the
+   directory of the last one, and importing a file of the chain again is a
+   cycle.
+*/
+#[derive(Debug, Default)]
+pub struct Modules {
+    cache: HashMap<PathBuf, GenericValue>,
+    loading: Vec<PathBuf>,
+}
+
+impl Modules {
+    pub fn enter(&mut self, path: &Path) {
+        // the script being run, the imports it makes are relative to it
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.loading.push(path);
+    }
+
+    pub fn import(&mut self, path: &str) -> Result<GenericValue, RuntimeError> {
+        let resolved = self.resolve(path)?;
+        if let Some(module) = self.cache.get(&resolved) {
+            return Ok(module.clone());
+        }
+        if let Some(start) = self.loading.iter().position(|p| *p == resolved) {
+            let chain: Vec<String> = self.loading[start..]
+                .iter()
+                .chain([&resolved])
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(RuntimeError::InvalidOperation(format!(
+                "circular import: {}",
+                chain.join(" -> ")
+            )));
+        }
+        let source = fs::read_to_string(&resolved).map_err(|e| {
+            RuntimeError::InvalidOperation(format!("cannot import \"{}\": {}", path, e))
+        })?;
+
+        self.loading.push(resolved.clone());
+        let globals = self.run(source);
+        self.loading.pop();
+
+        let globals = globals.ok_or_else(|| {
+            RuntimeError::InvalidOperation(format!("failed to import \"{}\"", path))
+        })?;
+        let name = resolved
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+        let module = GenericValue::from_module(Module { name, globals });
+        self.cache.insert(resolved, module.clone());
+        Ok(module)
+    }
+
+    fn resolve(&self, path: &str) -> Result<PathBuf, RuntimeError> {
+        let relative = match self.loading.last().and_then(|p| p.parent()) {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        };
+        relative.canonicalize().map_err(|e| {
+            RuntimeError::InvalidOperation(format!("cannot import \"{}\": {}", path, e))
+        })
+    }
+
+    fn run(&mut self, source: String) -> Option<HashMap<String, GenericValue>> {
+        // None when the module failed to compile or run, the error is already reported
+        let mut chunk = Chunk::default();
+        if !compile(source, &mut chunk) {
+            return None;
+        }
+        let mut vm = VirtualMachine::new();
+        vm.modules = mem::take(self);
+        let result = vm.run(&mut chunk);
+        *self = mem::take(&mut vm.modules);
+        match result {
+            InterpretResult::InterpretOk => Some(exported_globals(vm)),
+            _ => None,
+        }
+    }
+}
+
+fn exported_globals(vm: VirtualMachine) -> HashMap<String, GenericValue> {
+    // the built-in functions are defined in every file, they are not part of the module
+    let mut globals = vm.table.container;
+    globals.retain(|name, value| value.as_native().is_none_or(|n| n.name != *name));
+    globals
+}
@@ -0,0 +1,171 @@
+use std::cmp::Ordering;
+
+use crate::set::ValueSet;
+use crate::table::Table;
+use crate::values::GenericValue;
+use crate::vm::RuntimeError;
+
+/*
+   Built-in functions, defined as globals before a script starts running.
+   They are called like any other value with OpCall.
+*/
+pub fn define_natives(table: &mut Table) {
+    table.set(
+        String::from("set"),
+        GenericValue::from_native("set", set_native),
+    );
+    table.set(
+        String::from("sorted"),
+        GenericValue::from_native("sorted", sorted_native),
+    );
+    table.set(
+        String::from("str"),
+        GenericValue::from_native("str", str_native),
+    );
+    table.set(
+        String::from("repr"),
+        GenericValue::from_native("repr", repr_native),
+    );
+    table.set(
+        String::from("bytes"),
+        GenericValue::from_native("bytes", bytes_native),
+    );
+    table.set(
+        String::from("buffer"),
+        GenericValue::from_native("buffer", buffer_native),
+    );
+    table.set(
+        String::from("from_hex"),
+        GenericValue::from_native("from_hex", from_hex_native),
+    );
+    table.set(
+        String::from("freeze"),
+        GenericValue::from_native("freeze", freeze_native),
+    );
+}
+
+fn set_native(args: &[GenericValue]) -> Result<GenericValue, RuntimeError> {
+    // set() is empty, set(iterable) collects the distinct values of it
+    let mut set = ValueSet::new();
+    match args {
+        [] => {}
+        [iterable] => {
+            let mut iterator = iterable.iter()?;
+            while let Some(value) = iterator.iter_next() {
+                set.add(value)?;
+            }
+        }
+        _ => {
+            return Err(RuntimeError::InvalidOperation(format!(
+                "set() expects 0 or 1 arguments but got {}",
+                args.len()
+            )))
+        }
+    }
+    Ok(GenericValue::from_set(set))
+}
+
+fn sorted_native(args: &[GenericValue]) -> Result<GenericValue, RuntimeError> {
+    // a new list with the values of an iterable in ascending order, the sort is stable
+    let mut iterator = single_arg("sorted", args)?.iter()?;
+    let mut values = Vec::new();
+    while let Some(v) = iterator.iter_next() {
+        values.push(v);
+    }
+    GenericValue::check_sortable(&values)?;
+    // every pair is known to compare, the fallback is never used
+    values.sort_by(|v1, v2| v1.compare(v2).ok().flatten().unwrap_or(Ordering::Equal));
+    Ok(GenericValue::from_list(values))
+}
+
+fn str_native(args: &[GenericValue]) -> Result<GenericValue, RuntimeError> {
+    // the text print shows for the value
+    let value = single_arg("str", args)?;
+    Ok(GenericValue::from_string(value.to_string()))
+}
+
+fn repr_native(args: &[GenericValue]) -> Result<GenericValue, RuntimeError> {
+    // the debug form, with strings quoted
+    let value = single_arg("repr", args)?;
+    Ok(GenericValue::from_string(value.repr()))
+}
+
+fn single_arg<'a>(name: &str, args: &'a [GenericValue]) -> Result<&'a GenericValue, RuntimeError> {
+    match args {
+        [value] => Ok(value),
+        _ => Err(RuntimeError::InvalidOperation(format!(
+            "{}() expects 1 arguments but got {}",
+            name,
+            args.len()
+        ))),
+    }
+}
+
+fn bytes_native(args: &[GenericValue]) -> Result<GenericValue, RuntimeError> {
+    Ok(GenericValue::from_bytes(byte_args("bytes", args)?))
+}
+
+fn buffer_native(args: &[GenericValue]) -> Result<GenericValue, RuntimeError> {
+    Ok(GenericValue::from_buffer(byte_args("buffer", args)?))
+}
+
+fn byte_args(name: &str, args: &[GenericValue]) -> Result<Vec<u8>, RuntimeError> {
+    /*
+       bytes() and buffer() start empty, or copy their one argument: a
+       string is encoded as UTF-8, bytes and buffers are copied, and any
+       other iterable has to yield numbers between 0 and 255.
+    */
+    match args {
+        [] => Ok(Vec::new()),
+        [value] => {
+            if let Some(s) = value.as_string() {
+                return Ok(s.into_bytes());
+            }
+            if let Some(b) = value.as_bytes() {
+                return Ok(b);
+            }
+            let mut output = Vec::new();
+            let mut iterator = value.iter()?;
+            while let Some(v) = iterator.iter_next() {
+                output.push(v.as_byte()?);
+            }
+            Ok(output)
+        }
+        _ => Err(RuntimeError::InvalidOperation(format!(
+            "{}() expects 0 or 1 arguments but got {}",
+            name,
+            args.len()
+        ))),
+    }
+}
+
+fn from_hex_native(args: &[GenericValue]) -> Result<GenericValue, RuntimeError> {
+    // from_hex("00ff") is b"\x00\xff"
+    let value = single_arg("from_hex", args)?;
+    let hex = value.as_string().ok_or_else(|| {
+        RuntimeError::InvalidOperation(format!(
+            "from_hex() expects a string but got {}",
+            value.get_type_as_str()
+        ))
+    })?;
+    let invalid = || RuntimeError::InvalidOperation(format!("invalid hex string \"{}\"", hex));
+    if hex.len() % 2 != 0 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let mut output = Vec::with_capacity(hex.len() / 2);
+    for i in (0..hex.len()).step_by(2) {
+        output.push(u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid())?);
+    }
+    Ok(GenericValue::from_bytes(output))
+}
+
+fn freeze_native(args: &[GenericValue]) -> Result<GenericValue, RuntimeError> {
+    /*
+       Stops the sets and buffers in a value from changing, for every copy
+       of them, and returns the value. A list, tuple, record or variant is
+       frozen through its elements, so it can go into a set afterwards.
+    */
+    let value = single_arg("freeze", args)?;
+    value.freeze();
+    Ok(value.clone())
+}
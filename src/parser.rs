@@ -1,12 +1,28 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::errors::warning;
 use crate::scanner::Scanner;
 use crate::tokens::{Token, TokenType};
+use crate::types::{Binding, Type};
 
 #[derive(Default)]
 pub struct Parser {
     pub current: Option<Token>,
     pub previous: Option<Token>,
     pub had_error: bool,
+    pub warnings: usize,
     panic_mode: bool,
+    // (variant name, field count) of the enums declared so far, for match patterns
+    pub enums: HashMap<String, Vec<(String, usize)>>,
+    // jumps of the `?.` in the call chain being compiled, patched where the chain ends
+    pub optional_jumps: Vec<usize>,
+    // report type mismatches, only set by `lolang check --types`
+    pub check_types: bool,
+    // static type of the expression compiled last
+    pub expr_type: Type,
+    pub global_types: HashMap<String, Binding>,
+    // enums and records declared so far, usable in type annotations
+    pub type_names: HashSet<String>,
 }
 
 impl Parser {
@@ -15,7 +31,14 @@ impl Parser {
             current: None,
             previous: None,
             had_error: false,
+            warnings: 0,
             panic_mode: false,
+            enums: HashMap::new(),
+            optional_jumps: Vec::new(),
+            check_types: false,
+            expr_type: Type::Any,
+            global_types: HashMap::new(),
+            type_names: HashSet::new(),
         }
     }
 
@@ -29,8 +52,7 @@ impl Parser {
             if token_type != TokenType::ParseError {
                 break;
             }
-            self.panic_mode = true;
-            error_at(&token, &token.get_lexeme());
+            self.error_at(&token, &token.get_lexeme());
         }
     }
     pub fn consume(&mut self, token_type: TokenType, scanner: &mut Scanner, msg: &str) {
@@ -42,18 +64,73 @@ impl Parser {
         if token.get_type() == &token_type {
             self.advance(scanner);
         } else {
-            error_at(token, msg);
+            let token = token.clone();
+            self.error_at(&token, msg);
         }
     }
-}
 
-fn error_at(token: &Token, msg: &str) {
-    print!("[line {}] Error", token.get_line());
-    if token.get_type() == &TokenType::EOF {
-        print!(" at end")
-    } else if token.get_type() == &TokenType::ParseError {
-    } else {
-        print!(" at {}", token.get_lexeme())
+    pub fn error_at(&mut self, token: &Token, msg: &str) {
+        // only report the first error until the parser recovers
+        if self.panic_mode {
+            return;
+        }
+        self.panic_mode = true;
+        self.had_error = true;
+
+        print!("[line {}] Error", token.get_line());
+        if token.get_type() == &TokenType::EOF {
+            print!(" at end")
+        } else if token.get_type() == &TokenType::ParseError {
+        } else {
+            print!(" at {}", token.get_lexeme())
+        }
+        println!(": {}", msg);
+    }
+
+    pub fn warning(&mut self, line: usize, msg: &str) {
+        self.warnings += 1;
+        warning(line, msg);
+    }
+
+    pub fn type_error(&mut self, line: usize, msg: &str) {
+        // the annotations are erased when the checker doesn't run
+        if !self.check_types {
+            return;
+        }
+        self.had_error = true;
+        println!("[line {}] Type error: {}", line, msg);
+    }
+
+    pub fn global_type(&self, name: &str) -> Type {
+        self.global_types
+            .get(name)
+            .map(|b| b.ty.clone())
+            .unwrap_or(Type::Any)
+    }
+
+    pub fn define_global_type(&mut self, name: String, ty: Type, annotated: bool) {
+        self.global_types.insert(name, Binding { ty, annotated });
+    }
+
+    pub fn assign_global_type(&mut self, name: &str, ty: Type, line: usize) {
+        /*
+           An annotated variable keeps its type and every assignment is
+           checked against it. Without an annotation the type is inferred
+           from the initializer, and assigning a value of another type
+           means the checker no longer knows it.
+        */
+        let Some(binding) = self.global_types.get_mut(name) else {
+            return;
+        };
+        if !binding.annotated {
+            if binding.ty != ty {
+                binding.ty = Type::Any;
+            }
+            return;
+        }
+        if !binding.ty.accepts(&ty) {
+            let msg = format!("cannot assign {} to {}: {}", ty, name, binding.ty);
+            self.type_error(line, &msg);
+        }
     }
-    println!(": {}", msg);
 }
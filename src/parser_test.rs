@@ -2,6 +2,7 @@
 mod test {
     use crate::parser::Parser;
     use crate::scanner::Scanner;
+    use crate::tokens::TokenType;
 
     #[test]
     fn parse_string() {
@@ -10,6 +11,7 @@ mod test {
         let mut parser = Parser::new();
         parser.advance(&mut scanner);
         let obj = parser.current.unwrap();
-        // TODO: add tests
+        assert_eq!(obj.get_type(), &TokenType::String);
+        assert_eq!(obj.get_lexeme(), "abcde");
     }
 }
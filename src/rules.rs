@@ -4,10 +4,15 @@ use crate::tokens::TokenType;
 pub enum Precedence {
     PrecNone = 0,
     PrecAssignment,
+    PrecPipe,
+    PrecNullish,
     PrecOr,
     PrecAnd,
     PrecEquality,
     PrecComparison,
+    PrecBitOr,
+    PrecBitAnd,
+    PrecRange,
     PrecTerm,
     PrecFactor,
     PrecUnary,
@@ -20,15 +25,20 @@ impl Precedence {
         match usize {
             0 => Precedence::PrecNone,
             1 => Precedence::PrecAssignment,
-            2 => Precedence::PrecOr,
-            3 => Precedence::PrecAnd,
-            4 => Precedence::PrecEquality,
-            5 => Precedence::PrecComparison,
-            6 => Precedence::PrecTerm,
-            7 => Precedence::PrecFactor,
-            8 => Precedence::PrecUnary,
-            9 => Precedence::PrecCall,
-            10 => Precedence::PrecPrimary,
+            2 => Precedence::PrecPipe,
+            3 => Precedence::PrecNullish,
+            4 => Precedence::PrecOr,
+            5 => Precedence::PrecAnd,
+            6 => Precedence::PrecEquality,
+            7 => Precedence::PrecComparison,
+            8 => Precedence::PrecBitOr,
+            9 => Precedence::PrecBitAnd,
+            10 => Precedence::PrecRange,
+            11 => Precedence::PrecTerm,
+            12 => Precedence::PrecFactor,
+            13 => Precedence::PrecUnary,
+            14 => Precedence::PrecCall,
+            15 => Precedence::PrecPrimary,
             _ => panic!("Invalid Precedence"),
         }
     }
@@ -43,6 +53,17 @@ pub enum ParseFn {
     Binary,
     Grouping,
     Variable,
+    Interpolation,
+    List,
+    Dot,
+    Match,
+    Set,
+    Call,
+    Bytes,
+    Subscript,
+    OptionalChain,
+    Coalesce,
+    Pipe,
     Null,
 }
 
@@ -57,8 +78,8 @@ impl ParseRule {
         match token_type {
             TokenType::LeftParen => ParseRule {
                 prefix: ParseFn::Grouping,
-                infix: ParseFn::Null,
-                precedence: Precedence::PrecNone,
+                infix: ParseFn::Call,
+                precedence: Precedence::PrecCall,
             },
             TokenType::RightParen => ParseRule {
                 prefix: ParseFn::Null,
@@ -75,16 +96,26 @@ impl ParseRule {
                 infix: ParseFn::Null,
                 precedence: Precedence::PrecNone,
             },
-            TokenType::Comma => ParseRule {
+            TokenType::LeftBracket => ParseRule {
+                prefix: ParseFn::List,
+                infix: ParseFn::Subscript,
+                precedence: Precedence::PrecCall,
+            },
+            TokenType::RightBracket => ParseRule {
                 prefix: ParseFn::Null,
                 infix: ParseFn::Null,
                 precedence: Precedence::PrecNone,
             },
-            TokenType::Dot => ParseRule {
+            TokenType::Comma => ParseRule {
                 prefix: ParseFn::Null,
                 infix: ParseFn::Null,
                 precedence: Precedence::PrecNone,
             },
+            TokenType::Dot => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Dot,
+                precedence: Precedence::PrecCall,
+            },
             TokenType::Minus => ParseRule {
                 prefix: ParseFn::Unary,
                 infix: ParseFn::Binary,
@@ -110,6 +141,16 @@ impl ParseRule {
                 infix: ParseFn::Binary,
                 precedence: Precedence::PrecFactor,
             },
+            TokenType::Pipe => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Binary,
+                precedence: Precedence::PrecBitOr,
+            },
+            TokenType::Ampersand => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Binary,
+                precedence: Precedence::PrecBitAnd,
+            },
             TokenType::Bang => ParseRule {
                 prefix: ParseFn::Unary,
                 infix: ParseFn::Null,
@@ -150,6 +191,51 @@ impl ParseRule {
                 infix: ParseFn::Binary,
                 precedence: Precedence::PrecComparison,
             },
+            TokenType::DotDot => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Binary,
+                precedence: Precedence::PrecRange,
+            },
+            TokenType::DotDotEqual => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Binary,
+                precedence: Precedence::PrecRange,
+            },
+            TokenType::DotDotDot => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
+            TokenType::FatArrow => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
+            TokenType::QuestionMark => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
+            TokenType::QuestionDot => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::OptionalChain,
+                precedence: Precedence::PrecCall,
+            },
+            TokenType::QuestionQuestion => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Coalesce,
+                precedence: Precedence::PrecNullish,
+            },
+            TokenType::PipeArrow => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Pipe,
+                precedence: Precedence::PrecPipe,
+            },
+            TokenType::HashBrace => ParseRule {
+                prefix: ParseFn::Set,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
             TokenType::Identifier => ParseRule {
                 prefix: ParseFn::Variable,
                 infix: ParseFn::Null,
@@ -160,6 +246,16 @@ impl ParseRule {
                 infix: ParseFn::Null,
                 precedence: Precedence::PrecNone,
             },
+            TokenType::Bytes => ParseRule {
+                prefix: ParseFn::Bytes,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
+            TokenType::Interpolation => ParseRule {
+                prefix: ParseFn::Interpolation,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
             TokenType::Number => ParseRule {
                 prefix: ParseFn::Number,
                 infix: ParseFn::Null,
@@ -171,6 +267,11 @@ impl ParseRule {
                 infix: ParseFn::Null,
                 precedence: Precedence::PrecNone,
             },
+            TokenType::As => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
             TokenType::Class => ParseRule {
                 prefix: ParseFn::Null,
                 infix: ParseFn::Null,
@@ -181,6 +282,11 @@ impl ParseRule {
                 infix: ParseFn::Null,
                 precedence: Precedence::PrecNone,
             },
+            TokenType::Enum => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
             TokenType::False => ParseRule {
                 prefix: ParseFn::Literal,
                 infix: ParseFn::Null,
@@ -191,6 +297,11 @@ impl ParseRule {
                 infix: ParseFn::Null,
                 precedence: Precedence::PrecNone,
             },
+            TokenType::From => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
             TokenType::Fun => ParseRule {
                 prefix: ParseFn::Null,
                 infix: ParseFn::Null,
@@ -201,6 +312,21 @@ impl ParseRule {
                 infix: ParseFn::Null,
                 precedence: Precedence::PrecNone,
             },
+            TokenType::Import => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
+            TokenType::In => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
+            TokenType::Match => ParseRule {
+                prefix: ParseFn::Match,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
             TokenType::Nil => ParseRule {
                 prefix: ParseFn::Literal,
                 infix: ParseFn::Null,
@@ -216,6 +342,11 @@ impl ParseRule {
                 infix: ParseFn::Null,
                 precedence: Precedence::PrecNone,
             },
+            TokenType::Record => ParseRule {
+                prefix: ParseFn::Null,
+                infix: ParseFn::Null,
+                precedence: Precedence::PrecNone,
+            },
             TokenType::Return => ParseRule {
                 prefix: ParseFn::Null,
                 infix: ParseFn::Null,
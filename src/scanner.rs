@@ -1,11 +1,25 @@
 use crate::errors::error;
 use crate::tokens::{Token, TokenType};
+use unicode_ident::{is_xid_continue, is_xid_start};
 #[derive(Default)]
 pub struct Scanner {
     source: String,
     start: usize,
     current: usize,
     line: usize,
+    // brace depth of every `${` that is still open, innermost last
+    interpolation: Vec<usize>,
+    // set between save() and restore(), the tokens are scanned again later
+    lookahead: bool,
+}
+
+// A position in the source the scanner can rewind to, used for lookahead
+#[derive(Clone)]
+pub struct ScannerState {
+    start: usize,
+    current: usize,
+    line: usize,
+    interpolation: Vec<usize>,
 }
 
 impl Scanner {
@@ -15,6 +29,33 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            interpolation: Vec::new(),
+            lookahead: false,
+        }
+    }
+
+    pub fn save(&mut self) -> ScannerState {
+        // errors are not reported until restore(), they would be reported twice
+        self.lookahead = true;
+        ScannerState {
+            start: self.start,
+            current: self.current,
+            line: self.line,
+            interpolation: self.interpolation.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, state: ScannerState) {
+        self.start = state.start;
+        self.current = state.current;
+        self.line = state.line;
+        self.interpolation = state.interpolation;
+        self.lookahead = false;
+    }
+
+    fn error(&self, line: usize, msg: &str) {
+        if !self.lookahead {
+            error(line, msg);
         }
     }
 
@@ -23,7 +64,14 @@ impl Scanner {
     }
 
     pub fn scan_token(&mut self) -> Token {
-        self.skip_chars();
+        if let Err(line) = self.skip_chars() {
+            // the parser reports the error with the token's message
+            return Token::new(
+                TokenType::ParseError,
+                String::from("Unterminated block comment"),
+                line,
+            );
+        }
         self.start = self.current;
         if self.is_at_end() {
             return Token::new(TokenType::EOF, String::new(), self.line);
@@ -34,16 +82,69 @@ impl Scanner {
             // single character tokens
             '(' => TokenType::LeftParen,
             ')' => TokenType::RightParen,
-            '{' => TokenType::LeftBrace,
-            '}' => TokenType::RightBrace,
+            '{' => {
+                if let Some(depth) = self.interpolation.last_mut() {
+                    *depth += 1;
+                }
+                TokenType::LeftBrace
+            }
+            '}' => match self.interpolation.last_mut() {
+                // closes a `${`, the rest of the string literal follows
+                Some(0) => {
+                    self.interpolation.pop();
+                    self.match_string()
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    TokenType::RightBrace
+                }
+                None => TokenType::RightBrace,
+            },
+            '#' if self.match_sub_ch('{') => {
+                // a set literal, its '}' is counted like any other brace
+                if let Some(depth) = self.interpolation.last_mut() {
+                    *depth += 1;
+                }
+                TokenType::HashBrace
+            }
+            '[' => TokenType::LeftBracket,
+            ']' => TokenType::RightBracket,
             ',' => TokenType::Comma,
-            '.' => TokenType::Dot,
+            '.' => {
+                if !self.match_sub_ch('.') {
+                    TokenType::Dot
+                } else if self.match_sub_ch('.') {
+                    TokenType::DotDotDot
+                } else if self.match_sub_ch('=') {
+                    TokenType::DotDotEqual
+                } else {
+                    TokenType::DotDot
+                }
+            }
             '-' => TokenType::Minus,
             '+' => TokenType::Plus,
+            ':' => TokenType::Colon,
             ';' => TokenType::Semicolon,
             '*' => TokenType::Star,
+            '|' => {
+                if self.match_sub_ch('>') {
+                    TokenType::PipeArrow
+                } else {
+                    TokenType::Pipe
+                }
+            }
+            '&' => TokenType::Ampersand,
             '/' => TokenType::Slash,
             // double character tokens
+            '?' => {
+                if self.match_sub_ch('.') {
+                    TokenType::QuestionDot
+                } else if self.match_sub_ch('?') {
+                    TokenType::QuestionQuestion
+                } else {
+                    TokenType::QuestionMark
+                }
+            }
             '!' => {
                 if self.match_sub_ch('=') {
                     TokenType::BangEqual
@@ -54,6 +155,8 @@ impl Scanner {
             '=' => {
                 if self.match_sub_ch('=') {
                     TokenType::EqualEqual
+                } else if self.match_sub_ch('>') {
+                    TokenType::FatArrow
                 } else {
                     TokenType::Equal
                 }
@@ -73,27 +176,17 @@ impl Scanner {
                 }
             }
             // String Literals
-            '"' => {
-                while self.peek() != '"' && !self.is_at_end() {
-                    if self.peek() == '\n' {
-                        self.line += 1;
-                    }
-                    self.advance();
-                }
-                if self.is_at_end() {
-                    error(self.line, "Unterminated string literal");
-                    TokenType::ParseError
-                } else {
-                    self.advance(); // closing string
-                    TokenType::String
-                }
+            '"' => self.match_string(),
+            'b' if self.peek() == '"' => {
+                self.advance();
+                self.match_bytes()
             }
             _ => {
                 // put somewhere else
                 if ch.is_ascii_digit() {
                     self.match_number()
-                } else if ch.is_alphabetic() || ch == '_' {
-                    // Not sure why we need _
+                } else if is_xid_start(ch) || ch == '_' {
+                    // identifiers follow the Unicode XID rules, plus the leading '_'
                     self.match_identifier()
                 } else {
                     TokenType::ParseError
@@ -105,6 +198,14 @@ impl Scanner {
             TokenType::String => {
                 self.add_token_with_bound(token_type, self.start + 1, self.current - 1)
             }
+            // the escapes are left in, the compiler turns them into bytes
+            TokenType::Bytes => {
+                self.add_token_with_bound(token_type, self.start + 2, self.current - 1)
+            }
+            // strip the opening '"' (or '}') and the trailing "${"
+            TokenType::Interpolation => {
+                self.add_token_with_bound(token_type, self.start + 1, self.current - 2)
+            }
             TokenType::ParseError => Token::new(
                 token_type,
                 String::from(&self.source[self.start..self.current]),
@@ -114,7 +215,8 @@ impl Scanner {
         }
     }
 
-    fn skip_chars(&mut self) {
+    fn skip_chars(&mut self) -> Result<(), usize> {
+        // Err holds the line of a block comment that is never closed
         loop {
             let c = self.peek();
             match c {
@@ -125,22 +227,109 @@ impl Scanner {
                     self.line += 1;
                     self.advance();
                 }
-                '/' => {
-                    if self.peek_next() == '/' {
-                        while self.peek() != '\n' && !self.is_at_end() {
-                            self.advance();
-                        }
-                    } else {
-                        return;
+                '/' if self.peek_next() == '/' => {
+                    while self.peek() != '\n' && !self.is_at_end() {
+                        self.advance();
                     }
                 }
-                _ => return,
+                '/' if self.peek_next() == '*' => self.skip_block_comment()?,
+                _ => return Ok(()),
             };
         }
     }
 
+    fn skip_block_comment(&mut self) -> Result<(), usize> {
+        // block comments nest, the comment only ends once every
+        // opening slash-star got its own closing star-slash
+        let opening_line = self.line;
+        let mut depth = 0;
+        loop {
+            if self.is_at_end() {
+                return Err(opening_line);
+            }
+            match (self.peek(), self.peek_next()) {
+                ('/', '*') => {
+                    self.advance();
+                    self.advance();
+                    depth += 1;
+                }
+                ('*', '/') => {
+                    self.advance();
+                    self.advance();
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                ('\n', _) => {
+                    self.line += 1;
+                    self.advance();
+                }
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    fn match_string(&mut self) -> TokenType {
+        /*
+           "Hello ${name}, you are ${age + 1}"
+
+           is scanned as
+
+           Interpolation("Hello ") Identifier(name) Interpolation(", you are ")
+           Identifier(age) Plus Number(1) String("")
+
+           the '}' closing an interpolation resumes the string literal.
+        */
+        loop {
+            if self.is_at_end() {
+                self.error(self.line, "Unterminated string literal");
+                return TokenType::ParseError;
+            }
+            match self.peek() {
+                '"' => {
+                    self.advance(); // closing string
+                    return TokenType::String;
+                }
+                '$' if self.peek_next() == '{' => {
+                    self.advance();
+                    self.advance();
+                    self.interpolation.push(0);
+                    return TokenType::Interpolation;
+                }
+                '\n' => {
+                    self.line += 1;
+                    self.advance();
+                }
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    fn match_bytes(&mut self) -> TokenType {
+        // b"...", the escapes themselves are checked by the compiler
+        loop {
+            if self.is_at_end() {
+                self.error(self.line, "Unterminated bytes literal");
+                return TokenType::ParseError;
+            }
+            match self.advance() {
+                '"' => return TokenType::Bytes,
+                '\\' if matches!(self.peek(), '"' | '\\') => {
+                    self.advance();
+                }
+                '\n' => self.line += 1,
+                _ => {}
+            }
+        }
+    }
+
     fn match_identifier(&mut self) -> TokenType {
-        while self.peek().is_alphanumeric() || self.peek() == '_' {
+        while is_xid_continue(self.peek()) {
             self.advance();
         }
         // NOTE: this is the regular string comparison way
@@ -188,10 +377,10 @@ impl Scanner {
         if self.is_at_end() {
             return false;
         }
-        if expect != self.source.as_bytes()[self.current] as char {
+        if expect != self.peek() {
             return false;
         }
-        self.current += 1;
+        self.advance();
         true
     }
 
@@ -214,29 +403,20 @@ impl Scanner {
         /*
            Lookahead
         */
-        if self.is_at_end() {
-            '\0'
-        } else {
-            self.source.as_bytes()[self.current] as char
-        }
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            '\0'
-        } else {
-            self.source.as_bytes()[self.current + 1] as char
-        }
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
     fn advance(&mut self) -> char {
         /*
-        I'm Assuming the inputs were always ASCII characters.
-        Moreover, the entire logic could be implemented in more rusty way.
-        Since there are self.source.chars().nth() or something like that.
+        The source is walked code point by code point, while start and current
+        stay byte offsets so the lexemes can still be sliced out of the source.
         */
-        let output = self.source.as_bytes()[self.current] as char;
-        self.current += 1;
+        let output = self.peek();
+        self.current += output.len_utf8();
         output
     }
 }
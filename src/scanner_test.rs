@@ -167,4 +167,264 @@ mod test {
         let tokens = gen_tokens(&mut scanner);
         assert_eq!(tokens, vec![TokenType::Star, TokenType::EOF]);
     }
+    #[test]
+    fn scan_interpolation() {
+        let mut scanner = Scanner::new("\"a ${b} c\"".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Interpolation,
+                TokenType::Identifier,
+                TokenType::String,
+                TokenType::EOF
+            ]
+        );
+
+        let mut scanner = Scanner::new("\"a ${b} c ${d}\"".to_string());
+        assert_eq!(scanner.scan_token().get_lexeme(), "a ");
+        assert_eq!(scanner.scan_token().get_lexeme(), "b");
+        assert_eq!(scanner.scan_token().get_lexeme(), " c ");
+        assert_eq!(scanner.scan_token().get_lexeme(), "d");
+        let last = scanner.scan_token();
+        assert_eq!(last.get_type(), &TokenType::String);
+        assert_eq!(last.get_lexeme(), "");
+    }
+    #[test]
+    fn scan_nested_interpolation() {
+        let mut scanner = Scanner::new("\"a ${\"b ${c}\"} d\"".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Interpolation,
+                TokenType::Interpolation,
+                TokenType::Identifier,
+                TokenType::String,
+                TokenType::String,
+                TokenType::EOF
+            ]
+        );
+    }
+    #[test]
+    fn scan_interpolation_error() {
+        let mut scanner = Scanner::new("\"a ${b} c".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Interpolation,
+                TokenType::Identifier,
+                TokenType::ParseError,
+                TokenType::EOF
+            ]
+        );
+    }
+    #[test]
+    fn scan_block_comment() {
+        let mut scanner = Scanner::new("/* comment */ 1 /* a\nb */ +".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![TokenType::Number, TokenType::Plus, TokenType::EOF]
+        );
+    }
+    #[test]
+    fn scan_nested_block_comment() {
+        let mut scanner = Scanner::new("/* a /* b */ c */ 1".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(tokens, vec![TokenType::Number, TokenType::EOF]);
+    }
+    #[test]
+    fn scan_block_comment_lines() {
+        let mut scanner = Scanner::new("/*\n/*\n*/\n*/\n1".to_string());
+        let token = scanner.scan_token();
+        assert_eq!(token.get_type(), &TokenType::Number);
+        assert_eq!(token.get_line(), 5);
+    }
+    #[test]
+    fn scan_block_comment_error() {
+        let mut scanner = Scanner::new("1\n/* a /* b */\n".to_string());
+        scanner.scan_token();
+        let token = scanner.scan_token();
+        assert_eq!(token.get_type(), &TokenType::ParseError);
+        assert_eq!(token.get_lexeme(), "Unterminated block comment");
+        assert_eq!(token.get_line(), 2);
+        assert_eq!(scanner.scan_token().get_type(), &TokenType::EOF);
+    }
+    #[test]
+    fn scan_utf8_string() {
+        let mut scanner = Scanner::new("\"你好, wörld 🌏\" 1".to_string());
+        let token = scanner.scan_token();
+        assert_eq!(token.get_type(), &TokenType::String);
+        assert_eq!(token.get_lexeme(), "你好, wörld 🌏");
+        assert_eq!(scanner.scan_token().get_type(), &TokenType::Number);
+    }
+    #[test]
+    fn scan_utf8_identifier() {
+        let mut scanner = Scanner::new("變數 café _x1 αβγ".to_string());
+        let lexemes: Vec<String> = (0..4).map(|_| scanner.scan_token().get_lexeme()).collect();
+        assert_eq!(lexemes, vec!["變數", "café", "_x1", "αβγ"]);
+        assert_eq!(scanner.scan_token().get_type(), &TokenType::EOF);
+    }
+    #[test]
+    fn scan_utf8_comment() {
+        let mut scanner = Scanner::new("// 註解 ✓\n/* 區塊 */ var".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(tokens, vec![TokenType::Var, TokenType::EOF]);
+    }
+    #[test]
+    fn scan_utf8_invalid_character() {
+        let mut scanner = Scanner::new("€ 1".to_string());
+        let token = scanner.scan_token();
+        assert_eq!(token.get_type(), &TokenType::ParseError);
+        assert_eq!(token.get_lexeme(), "€");
+        assert_eq!(scanner.scan_token().get_type(), &TokenType::Number);
+    }
+    #[test]
+    fn scan_range() {
+        let mut scanner = Scanner::new("1..5".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Number,
+                TokenType::DotDot,
+                TokenType::Number,
+                TokenType::EOF
+            ]
+        );
+
+        let mut scanner = Scanner::new("a..=b.c".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Identifier,
+                TokenType::DotDotEqual,
+                TokenType::Identifier,
+                TokenType::Dot,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+    }
+    #[test]
+    fn scan_dotdotdot() {
+        let mut scanner = Scanner::new("[a, ...b]".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::LeftBracket,
+                TokenType::Identifier,
+                TokenType::Comma,
+                TokenType::DotDotDot,
+                TokenType::Identifier,
+                TokenType::RightBracket,
+                TokenType::EOF
+            ]
+        );
+    }
+    #[test]
+    fn scan_fat_arrow() {
+        let mut scanner = Scanner::new("_ => = >".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Identifier,
+                TokenType::FatArrow,
+                TokenType::Equal,
+                TokenType::Greater,
+                TokenType::EOF
+            ]
+        );
+    }
+    #[test]
+    fn scan_set_literal_and_operators() {
+        let mut scanner = Scanner::new("#{1} | a & b".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::HashBrace,
+                TokenType::Number,
+                TokenType::RightBrace,
+                TokenType::Pipe,
+                TokenType::Identifier,
+                TokenType::Ampersand,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+    }
+    #[test]
+    fn scan_set_literal_in_interpolation() {
+        let mut scanner = Scanner::new("\"${#{1}}\"".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Interpolation,
+                TokenType::HashBrace,
+                TokenType::Number,
+                TokenType::RightBrace,
+                TokenType::String,
+                TokenType::EOF
+            ]
+        );
+    }
+    #[test]
+    fn scan_bytes_literal() {
+        let mut scanner = Scanner::new(r#"b"a\"b" b x[0]"#.to_string());
+        let token = scanner.scan_token();
+        assert_eq!(*token.get_type(), TokenType::Bytes);
+        assert_eq!(token.get_lexeme(), r#"a\"b"#);
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::LeftBracket,
+                TokenType::Number,
+                TokenType::RightBracket,
+                TokenType::EOF
+            ]
+        );
+    }
+    #[test]
+    fn scan_question_tokens() {
+        let mut scanner = Scanner::new("a?.b ?? c ?".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Identifier,
+                TokenType::QuestionDot,
+                TokenType::Identifier,
+                TokenType::QuestionQuestion,
+                TokenType::Identifier,
+                TokenType::QuestionMark,
+                TokenType::EOF
+            ]
+        );
+    }
+    #[test]
+    fn scan_pipe_arrow() {
+        let mut scanner = Scanner::new("a |> f | b".to_string());
+        let tokens = gen_tokens(&mut scanner);
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Identifier,
+                TokenType::PipeArrow,
+                TokenType::Identifier,
+                TokenType::Pipe,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+    }
 }
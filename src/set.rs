@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+
+use crate::values::GenericValue;
+use crate::vm::RuntimeError;
+
+/*
+   A hash set of values that iterates in insertion order, so printing and
+   for-in loops over a set are deterministic.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct ValueSet {
+    items: Vec<GenericValue>,
+    index: HashSet<GenericValue>,
+    frozen: bool,
+}
+
+impl ValueSet {
+    pub fn new() -> ValueSet {
+        ValueSet {
+            items: Vec::new(),
+            index: HashSet::new(),
+            frozen: false,
+        }
+    }
+
+    pub fn add(&mut self, value: GenericValue) -> Result<bool, RuntimeError> {
+        // returns false if the value was already in the set
+        self.check_mutable()?;
+        value.check_hashable()?;
+        Ok(self.insert(value))
+    }
+
+    fn insert(&mut self, value: GenericValue) -> bool {
+        if !self.index.insert(value.clone()) {
+            return false;
+        }
+        self.items.push(value);
+        true
+    }
+
+    pub fn remove(&mut self, value: &GenericValue) -> Result<bool, RuntimeError> {
+        self.check_mutable()?;
+        if !self.index.remove(value) {
+            return Ok(false);
+        }
+        self.items.retain(|v| v != value);
+        Ok(true)
+    }
+
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub fn check_mutable(&self) -> Result<(), RuntimeError> {
+        if self.frozen {
+            return Err(RuntimeError::InvalidOperation(String::from(
+                "cannot modify a frozen set",
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, value: &GenericValue) -> bool {
+        self.index.contains(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn items(&self) -> &[GenericValue] {
+        &self.items
+    }
+
+    pub fn union(&self, other: &ValueSet) -> ValueSet {
+        let mut output = self.clone();
+        output.frozen = false;
+        for v in other.items() {
+            output.insert(v.clone());
+        }
+        output
+    }
+
+    pub fn intersection(&self, other: &ValueSet) -> ValueSet {
+        let mut output = ValueSet::new();
+        for v in self.items().iter().filter(|v| other.contains(v)) {
+            output.insert(v.clone());
+        }
+        output
+    }
+
+    pub fn difference(&self, other: &ValueSet) -> ValueSet {
+        let mut output = ValueSet::new();
+        for v in self.items().iter().filter(|v| !other.contains(v)) {
+            output.insert(v.clone());
+        }
+        output
+    }
+}
+
+impl PartialEq for ValueSet {
+    fn eq(&self, other: &Self) -> bool {
+        // sets are equal regardless of insertion order
+        self.len() == other.len() && self.items().iter().all(|v| other.contains(v))
+    }
+}
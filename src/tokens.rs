@@ -6,6 +6,8 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -13,6 +15,8 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Pipe,
+    Ampersand,
     Colon,
     QuestionMark,
 
@@ -25,23 +29,40 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    DotDot,
+    DotDotEqual,
+    DotDotDot,
+    FatArrow,
+    HashBrace,
+    QuestionDot,
+    QuestionQuestion,
+    PipeArrow,
 
     // Literals
     Identifier,
     String,
+    Bytes,
+    Interpolation,
     Number,
 
     // Keywords
     And,
+    As,
     Class,
     Else,
+    Enum,
     False,
+    From,
     Fun,
     For,
     If,
+    Import,
+    In,
+    Match,
     Nil,
     Or,
     Print,
+    Record,
     Return,
     Super,
     This,
@@ -62,6 +83,8 @@ impl TokenType {
             TokenType::RightParen => ")",
             TokenType::LeftBrace => "{",
             TokenType::RightBrace => "}",
+            TokenType::LeftBracket => "[",
+            TokenType::RightBracket => "]",
             TokenType::Comma => ",",
             TokenType::Dot => ".",
             TokenType::Minus => "-",
@@ -70,6 +93,8 @@ impl TokenType {
             TokenType::Semicolon => ";",
             TokenType::Slash => "/",
             TokenType::Star => "*",
+            TokenType::Pipe => "|",
+            TokenType::Ampersand => "&",
             TokenType::QuestionMark => "?",
 
             // One or two character tokens
@@ -81,23 +106,40 @@ impl TokenType {
             TokenType::GreaterEqual => ">=",
             TokenType::Less => "<",
             TokenType::LessEqual => "<=",
+            TokenType::DotDot => "..",
+            TokenType::DotDotEqual => "..=",
+            TokenType::DotDotDot => "...",
+            TokenType::FatArrow => "=>",
+            TokenType::HashBrace => "#{",
+            TokenType::QuestionDot => "?.",
+            TokenType::QuestionQuestion => "??",
+            TokenType::PipeArrow => "|>",
 
             // Literals
             TokenType::Identifier => "<Identifier>",
             TokenType::String => "<String>",
+            TokenType::Bytes => "<Bytes>",
+            TokenType::Interpolation => "<Interpolation>",
             TokenType::Number => "<Number>",
 
             // Keywords
             TokenType::And => "and",
+            TokenType::As => "as",
             TokenType::Class => "class",
             TokenType::Else => "else",
+            TokenType::Enum => "enum",
             TokenType::False => "false",
+            TokenType::From => "from",
             TokenType::Fun => "fun",
             TokenType::For => "for",
             TokenType::If => "if",
+            TokenType::Import => "import",
+            TokenType::In => "in",
+            TokenType::Match => "match",
             TokenType::Nil => "nil",
             TokenType::Or => "or",
             TokenType::Print => "print",
+            TokenType::Record => "record",
             TokenType::Return => "return",
             TokenType::Super => "super",
             TokenType::This => "this",
@@ -114,15 +156,22 @@ impl TokenType {
     pub fn keyword_to_token(s: &str) -> Option<TokenType> {
         match s {
             "and" => Some(TokenType::And),
+            "as" => Some(TokenType::As),
             "class" => Some(TokenType::Class),
             "else" => Some(TokenType::Else),
+            "enum" => Some(TokenType::Enum),
             "false" => Some(TokenType::False),
+            "from" => Some(TokenType::From),
             "fun" => Some(TokenType::Fun),
             "for" => Some(TokenType::For),
             "if" => Some(TokenType::If),
+            "import" => Some(TokenType::Import),
+            "in" => Some(TokenType::In),
+            "match" => Some(TokenType::Match),
             "nil" => Some(TokenType::Nil),
             "or" => Some(TokenType::Or),
             "print" => Some(TokenType::Print),
+            "record" => Some(TokenType::Record),
             "return" => Some(TokenType::Return),
             "super" => Some(TokenType::Super),
             "this" => Some(TokenType::This),
@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+use crate::tokens::TokenType;
+
+/*
+   The static types of the optional type checker. Annotations such as
+   `var x: Int = 1;` are parsed by the compiler and erased, the checker only
+   uses them to report mismatches in `lolang check --types`.
+
+   Any is the type of every expression the checker can't work out, it is
+   compatible with every other type, so the checker never reports code it
+   doesn't understand.
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Type {
+    #[default]
+    Any,
+    Nil,
+    Bool,
+    Int, // a number known to be whole, accepted wherever a Num is
+    Num,
+    Str,
+    Bytes,
+    List,
+    Tuple,
+    Set,
+    Range,
+    Named(String),       // an enum or a record
+    Optional(Box<Type>), // Int? is an Int or nil
+}
+
+// what the checker knows about a global variable
+#[derive(Clone, Debug)]
+pub struct Binding {
+    pub ty: Type,
+    pub annotated: bool,
+}
+
+impl Type {
+    pub fn from_name(name: &str, declared: &HashSet<String>) -> Option<Type> {
+        let ty = match name {
+            "Any" => Type::Any,
+            "Nil" => Type::Nil,
+            "Bool" => Type::Bool,
+            "Int" => Type::Int,
+            "Num" => Type::Num,
+            "Str" => Type::Str,
+            "Bytes" => Type::Bytes,
+            "List" => Type::List,
+            "Tuple" => Type::Tuple,
+            "Set" => Type::Set,
+            "Range" => Type::Range,
+            _ if declared.contains(name) => Type::Named(name.to_string()),
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    pub fn number_literal(lexeme: &str) -> Type {
+        if lexeme.contains('.') {
+            Type::Num
+        } else {
+            Type::Int
+        }
+    }
+
+    pub fn accepts(&self, value: &Type) -> bool {
+        // whether a value of type `value` can be stored in a variable of this type
+        match (self, value) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Optional(_), Type::Nil) => true,
+            (Type::Optional(inner), Type::Optional(value)) => inner.accepts(value),
+            (Type::Optional(inner), value) => inner.accepts(value),
+            (Type::Num, Type::Int) => true,
+            (declared, value) => declared == value,
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, Type::Int | Type::Num)
+    }
+
+    pub fn unary(op: TokenType, operand: &Type) -> Option<Type> {
+        // None when the operator can't be applied to the operand
+        match op {
+            TokenType::Bang => Some(Type::Bool),
+            TokenType::Minus if operand.is_number() => Some(operand.clone()),
+            TokenType::Minus if *operand == Type::Any => Some(Type::Any),
+            _ => None,
+        }
+    }
+
+    pub fn binary(op: TokenType, left: &Type, right: &Type) -> Option<Type> {
+        // the type of `left op right`, None when the operands don't support the operator
+        match op {
+            TokenType::EqualEqual | TokenType::BangEqual => return Some(Type::Bool),
+            TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual => {
+                let comparable = (left.is_number() && right.is_number())
+                    || (left == right
+                        && matches!(left, Type::Str | Type::Bytes | Type::List | Type::Tuple));
+                if comparable || *left == Type::Any || *right == Type::Any {
+                    return Some(Type::Bool);
+                }
+                return None;
+            }
+            _ => {}
+        }
+        if *left == Type::Any || *right == Type::Any {
+            return Some(Type::Any);
+        }
+        match (op, left, right) {
+            (TokenType::Plus | TokenType::Minus | TokenType::Star, Type::Int, Type::Int) => {
+                Some(Type::Int)
+            }
+            (TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash, l, r)
+                if l.is_number() && r.is_number() =>
+            {
+                Some(Type::Num)
+            }
+            (TokenType::DotDot | TokenType::DotDotEqual, l, r)
+                if l.is_number() && r.is_number() =>
+            {
+                Some(Type::Range)
+            }
+            (TokenType::Plus, Type::Str, Type::Str) => Some(Type::Str),
+            (TokenType::Plus, Type::Bytes, Type::Bytes) => Some(Type::Bytes),
+            (TokenType::Minus | TokenType::Pipe | TokenType::Ampersand, Type::Set, Type::Set) => {
+                Some(Type::Set)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Any => write!(f, "Any"),
+            Type::Nil => write!(f, "Nil"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Int => write!(f, "Int"),
+            Type::Num => write!(f, "Num"),
+            Type::Str => write!(f, "Str"),
+            Type::Bytes => write!(f, "Bytes"),
+            Type::List => write!(f, "List"),
+            Type::Tuple => write!(f, "Tuple"),
+            Type::Set => write!(f, "Set"),
+            Type::Range => write!(f, "Range"),
+            Type::Named(name) => write!(f, "{}", name),
+            Type::Optional(inner) => write!(f, "{}?", inner),
+        }
+    }
+}
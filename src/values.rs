@@ -1,17 +1,222 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::mem::discriminant;
+use std::ops::{Add, BitAnd, BitOr, Div, Mul, Neg, Sub};
+use std::rc::Rc;
 
+use crate::set::ValueSet;
 use crate::vm::RuntimeError;
 
 #[derive(Clone, Debug)]
 pub enum Value {
     Str(String),
+    List(Vec<GenericValue>),
+    Tuple(Vec<GenericValue>),
+    Range(Range),
+    Iterator(ValueIterator),
+    Enum(Enum),
+    Variant(Variant),
+    Pattern(Box<MatchPattern>),
+    // sets are mutable, every copy of a set value shares the same storage
+    Set(Rc<RefCell<ValueSet>>),
+    Native(Native),
+    Bytes(Vec<u8>),
+    // a mutable byte buffer, shared between copies like a set
+    Buffer(Rc<RefCell<ByteBuffer>>),
+    Module(Rc<Module>),
+    RecordType(RecordType),
+    Record(Record),
+}
+
+pub type NativeFn = fn(&[GenericValue]) -> Result<GenericValue, RuntimeError>;
+
+// a built-in function implemented in Rust, such as set()
+#[derive(Clone, Debug)]
+pub struct Native {
+    pub name: String,
+    pub function: NativeFn,
+}
+
+// the globals an imported file defined, read with module.name
+#[derive(Debug)]
+pub struct Module {
+    pub name: String,
+    pub globals: HashMap<String, GenericValue>,
+}
+
+// the storage of a buffer, freeze() stops it from changing
+#[derive(Debug, Default)]
+pub struct ByteBuffer {
+    pub bytes: Vec<u8>,
+    pub frozen: bool,
+}
+
+impl ByteBuffer {
+    pub fn bytes_mut(&mut self) -> Result<&mut Vec<u8>, RuntimeError> {
+        if self.frozen {
+            return Err(RuntimeError::InvalidOperation(String::from(
+                "cannot modify a frozen buffer",
+            )));
+        }
+        Ok(&mut self.bytes)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Range {
+    pub start: f64,
+    pub end: f64,
+    pub inclusive: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnumVariant {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+// enum Shape { Circle(r), Rect(w, h), Empty }
+#[derive(Clone, Debug, PartialEq)]
+pub struct Enum {
+    pub name: String,
+    pub variants: Vec<EnumVariant>,
+}
+
+// record Point(x, y), calling it builds a Point
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordType {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+// a value such as Point(x: 1, y: 2), compared and hashed by its field values
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub fields: Vec<String>,
+    pub values: Vec<GenericValue>,
+}
+
+// a tagged value such as Shape.Rect(2, 3)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variant {
+    pub enum_name: String,
+    pub name: String,
+    pub fields: Vec<GenericValue>,
+}
+
+/*
+   A compiled match arm pattern, stored in the constant pool and tested by
+   OpMatch. Bindings only keep their position, the compiler defines the
+   variables from the values matches() collects.
+*/
+#[derive(Clone, Debug)]
+pub enum MatchPattern {
+    Wildcard,
+    Binding,
+    Literal(GenericValue),
+    Variant {
+        enum_name: String,
+        name: String,
+        fields: Vec<MatchPattern>,
+    },
+}
+
+impl MatchPattern {
+    pub fn matches(&self, value: &GenericValue, bindings: &mut Vec<GenericValue>) -> bool {
+        match self {
+            MatchPattern::Wildcard => true,
+            MatchPattern::Binding => {
+                bindings.push(value.clone());
+                true
+            }
+            MatchPattern::Literal(literal) => literal == value,
+            MatchPattern::Variant {
+                enum_name,
+                name,
+                fields,
+            } => match value.as_variant() {
+                Some(v)
+                    if &v.enum_name == enum_name
+                        && &v.name == name
+                        && v.fields.len() == fields.len() =>
+                {
+                    fields
+                        .iter()
+                        .zip(v.fields.iter())
+                        .all(|(pattern, field)| pattern.matches(field, bindings))
+                }
+                _ => false,
+            },
+        }
+    }
+
+    pub fn is_irrefutable(&self) -> bool {
+        matches!(self, MatchPattern::Wildcard | MatchPattern::Binding)
+    }
+}
+
+/*
+   The state of a running for-in loop. It lives in a stack slot and is
+   advanced in place by OpIterNext, so stepping through a range never
+   allocates.
+*/
+#[derive(Clone, Debug)]
+pub enum ValueIterator {
+    Range {
+        next: f64,
+        end: f64,
+        inclusive: bool,
+    },
+    Str {
+        s: String,
+        offset: usize,
+    },
+    List {
+        values: Vec<GenericValue>,
+        index: usize,
+    },
+}
+
+impl ValueIterator {
+    pub fn advance(&mut self) -> Option<GenericValue> {
+        match self {
+            ValueIterator::Range {
+                next,
+                end,
+                inclusive,
+            } => {
+                if *next < *end || (*inclusive && *next == *end) {
+                    let value = *next;
+                    *next += 1.0;
+                    Some(GenericValue::from_f64(value))
+                } else {
+                    None
+                }
+            }
+            ValueIterator::Str { s, offset } => {
+                let ch = s[*offset..].chars().next()?;
+                *offset += ch.len_utf8();
+                Some(GenericValue::from_string(ch.to_string()))
+            }
+            ValueIterator::List { values, index } => {
+                let value = values.get(*index)?.clone();
+                *index += 1;
+                Some(value)
+            }
+        }
+    }
 }
 #[derive(Clone, Debug)]
 pub struct DynamicSizeObject {
     // looks like a node or something, for future gc purposes
     value: Value,
+    #[allow(dead_code)]
     prev: Option<Box<DynamicSizeObject>>, // Pure heap alloc, find a way free
+    #[allow(dead_code)]
     next: Option<Box<DynamicSizeObject>>,
 }
 
@@ -23,6 +228,63 @@ impl DynamicSizeObject {
             next: None,
         }
     }
+    pub fn from_list(values: Vec<GenericValue>) -> DynamicSizeObject {
+        DynamicSizeObject {
+            value: Value::List(values),
+            prev: None,
+            next: None,
+        }
+    }
+    fn from_value(value: Value) -> DynamicSizeObject {
+        DynamicSizeObject {
+            value,
+            prev: None,
+            next: None,
+        }
+    }
+    pub fn from_tuple(values: Vec<GenericValue>) -> DynamicSizeObject {
+        DynamicSizeObject {
+            value: Value::Tuple(values),
+            prev: None,
+            next: None,
+        }
+    }
+    pub fn from_range(range: Range) -> DynamicSizeObject {
+        DynamicSizeObject {
+            value: Value::Range(range),
+            prev: None,
+            next: None,
+        }
+    }
+    pub fn from_iterator(iterator: ValueIterator) -> DynamicSizeObject {
+        DynamicSizeObject {
+            value: Value::Iterator(iterator),
+            prev: None,
+            next: None,
+        }
+    }
+}
+
+impl DynamicSizeObject {
+    fn get_type_as_str(&self) -> String {
+        match &self.value {
+            Value::Str(_) => String::from("string"),
+            Value::List(_) => String::from("list"),
+            Value::Tuple(_) => String::from("tuple"),
+            Value::Range(_) => String::from("range"),
+            Value::Iterator(_) => String::from("iterator"),
+            Value::Enum(_) => String::from("enum"),
+            Value::Variant(v) => v.enum_name.clone(),
+            Value::Pattern(_) => String::from("pattern"),
+            Value::Set(_) => String::from("set"),
+            Value::Native(_) => String::from("native function"),
+            Value::Bytes(_) => String::from("bytes"),
+            Value::Buffer(_) => String::from("buffer"),
+            Value::Module(_) => String::from("module"),
+            Value::RecordType(_) => String::from("record"),
+            Value::Record(r) => r.name.clone(),
+        }
+    }
 }
 
 impl Add for DynamicSizeObject {
@@ -32,10 +294,27 @@ impl Add for DynamicSizeObject {
             (Value::Str(s1), Value::Str(s2)) => {
                 Ok(DynamicSizeObject::from_string(s1.to_owned() + s2))
             }
-            _ => Err(RuntimeError::UnsupportedOperation(
-                String::from("Generic object type1 To be implemented"),
-                String::from("Generic object type2 To be implemented"),
-            )),
+            // concatenating bytes or buffers always gives immutable bytes
+            _ => match (self.byte_contents(), rhs.byte_contents()) {
+                (Some(mut b1), Some(b2)) => {
+                    b1.extend(b2);
+                    Ok(DynamicSizeObject::from_value(Value::Bytes(b1)))
+                }
+                _ => Err(RuntimeError::UnsupportedOperation(
+                    self.get_type_as_str(),
+                    rhs.get_type_as_str(),
+                )),
+            },
+        }
+    }
+}
+
+impl DynamicSizeObject {
+    fn byte_contents(&self) -> Option<Vec<u8>> {
+        match &self.value {
+            Value::Bytes(b) => Some(b.clone()),
+            Value::Buffer(b) => Some(b.borrow().bytes.clone()),
+            _ => None,
         }
     }
 }
@@ -44,11 +323,139 @@ impl PartialEq for DynamicSizeObject {
     fn eq(&self, other: &Self) -> bool {
         match (&self.value, &other.value) {
             (Value::Str(s1), Value::Str(s2)) => s1 == s2,
+            (Value::List(l1), Value::List(l2)) => l1 == l2,
+            (Value::Tuple(t1), Value::Tuple(t2)) => t1 == t2,
+            (Value::Range(r1), Value::Range(r2)) => r1 == r2,
+            (Value::Enum(e1), Value::Enum(e2)) => e1 == e2,
+            (Value::Variant(v1), Value::Variant(v2)) => v1 == v2,
+            (Value::Set(s1), Value::Set(s2)) => *s1.borrow() == *s2.borrow(),
+            (Value::Bytes(b1), Value::Bytes(b2)) => b1 == b2,
+            (Value::Buffer(b1), Value::Buffer(b2)) => b1.borrow().bytes == b2.borrow().bytes,
+            // a file is only loaded once, so a module is only equal to itself
+            (Value::Module(m1), Value::Module(m2)) => Rc::ptr_eq(m1, m2),
+            (Value::RecordType(r1), Value::RecordType(r2)) => r1 == r2,
+            (Value::Record(r1), Value::Record(r2)) => r1 == r2,
             _ => false,
         }
     }
 }
 
+impl Hash for DynamicSizeObject {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // must agree with PartialEq: values that compare equal hash equally
+        discriminant(&self.value).hash(state);
+        match &self.value {
+            Value::Str(s) => s.hash(state),
+            Value::Bytes(b) => b.hash(state),
+            Value::List(values) | Value::Tuple(values) => values.hash(state),
+            Value::Range(r) => {
+                hash_f64(r.start, state);
+                hash_f64(r.end, state);
+                r.inclusive.hash(state);
+            }
+            Value::Enum(e) => e.name.hash(state),
+            Value::Variant(v) => {
+                v.enum_name.hash(state);
+                v.name.hash(state);
+                v.fields.hash(state);
+            }
+            Value::Set(set) => {
+                // sets are equal in any order, so the element hashes are combined in any order
+                let set = set.borrow();
+                let mut combined: u64 = 0;
+                for v in set.items() {
+                    let mut hasher = DefaultHasher::new();
+                    v.hash(&mut hasher);
+                    combined = combined.wrapping_add(hasher.finish());
+                }
+                set.len().hash(state);
+                combined.hash(state);
+            }
+            Value::Buffer(b) => b.borrow().bytes.hash(state),
+            Value::RecordType(r) => r.name.hash(state),
+            Value::Record(r) => {
+                r.name.hash(state);
+                r.values.hash(state);
+            }
+            // never stored in a set, see check_hashable
+            Value::Iterator(_) | Value::Pattern(_) | Value::Native(_) | Value::Module(_) => {}
+        }
+    }
+}
+
+fn check_comparable(values: &[&GenericValue]) -> Result<(), RuntimeError> {
+    // the values must all be numbers but no nan, strings, bytes, lists or tuples
+    if values.len() < 2 {
+        return Ok(());
+    }
+    let first = values[0];
+    let mut sequences: Vec<&Vec<GenericValue>> = Vec::new();
+    for v in values {
+        match (first, v) {
+            (GenericValueType::Number(_), GenericValueType::Number(n)) => {
+                if n.is_nan() {
+                    return Err(RuntimeError::InvalidOperation(String::from(
+                        "cannot sort nan",
+                    )));
+                }
+                continue;
+            }
+            (GenericValueType::Object(o1), GenericValueType::Object(o2)) => {
+                match (&o1.value, &o2.value) {
+                    (Value::Str(_), Value::Str(_)) | (Value::Bytes(_), Value::Bytes(_)) => continue,
+                    (Value::List(_), Value::List(l)) | (Value::Tuple(_), Value::Tuple(l)) => {
+                        sequences.push(l);
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        return Err(RuntimeError::InvalidOperation(format!(
+            "cannot compare {} and {}",
+            first.get_type_as_str(),
+            v.get_type_as_str()
+        )));
+    }
+    if sequences.is_empty() {
+        return Ok(());
+    }
+    check_sequences(&sequences, 0)
+}
+
+// the values are only borrowed while grouping, none of them can change meanwhile
+#[allow(clippy::mutable_key_type)]
+fn check_sequences(sequences: &[&Vec<GenericValue>], i: usize) -> Result<(), RuntimeError> {
+    // the sequences agree on their first i elements, so position i decides between them
+    let column: Vec<&GenericValue> = sequences.iter().filter_map(|l| l.get(i)).collect();
+    if column.iter().any(|v| *v != column[0]) {
+        check_comparable(&column)?;
+    }
+    // only sequences that also agree at position i get compared further
+    let mut groups: Vec<Vec<&Vec<GenericValue>>> = Vec::new();
+    let mut group_of: HashMap<&GenericValue, usize> = HashMap::new();
+    for l in sequences {
+        if let Some(v) = l.get(i) {
+            let index = *group_of.entry(v).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[index].push(l);
+        }
+    }
+    for group in groups.iter().filter(|g| g.len() > 1) {
+        check_sequences(group, i + 1)?;
+    }
+    Ok(())
+}
+
+fn hash_f64<H: Hasher>(value: f64, state: &mut H) {
+    // 0.0 == -0.0, so both have to hash the same
+    let value = if value == 0.0 { 0.0 } else { value };
+    value.to_bits().hash(state);
+}
+
 #[derive(Clone, Debug)]
 pub enum GenericValueType {
     Bool(bool),
@@ -58,14 +465,12 @@ pub enum GenericValueType {
 }
 
 impl GenericValueType {
-    fn get_type_as_str(&self) -> String {
+    pub fn get_type_as_str(&self) -> String {
         match self {
             GenericValueType::Bool(_) => String::from("bool"),
             GenericValueType::Number(_) => String::from("number"),
             GenericValueType::Nil => String::from("nil"),
-            GenericValueType::Object(obj) => match obj.value.clone() {
-                Value::Str(s) => s,
-            },
+            GenericValueType::Object(obj) => obj.get_type_as_str(),
         }
     }
 }
@@ -85,6 +490,63 @@ impl GenericValue {
     pub fn from_string(value: String) -> GenericValue {
         GenericValue::Object(DynamicSizeObject::from_string(value))
     }
+    pub fn from_list(values: Vec<GenericValue>) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_list(values))
+    }
+    pub fn from_tuple(values: Vec<GenericValue>) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_tuple(values))
+    }
+    pub fn from_range(start: f64, end: f64, inclusive: bool) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_range(Range {
+            start,
+            end,
+            inclusive,
+        }))
+    }
+    pub fn from_enum(value: Enum) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_value(Value::Enum(value)))
+    }
+    pub fn from_variant(value: Variant) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_value(Value::Variant(value)))
+    }
+    pub fn from_pattern(value: MatchPattern) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_value(Value::Pattern(Box::new(
+            value,
+        ))))
+    }
+    pub fn from_set(value: ValueSet) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_value(Value::Set(Rc::new(
+            RefCell::new(value),
+        ))))
+    }
+    pub fn from_bytes(value: Vec<u8>) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_value(Value::Bytes(value)))
+    }
+    pub fn from_buffer(value: Vec<u8>) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_value(Value::Buffer(Rc::new(
+            RefCell::new(ByteBuffer {
+                bytes: value,
+                frozen: false,
+            }),
+        ))))
+    }
+    pub fn from_native(name: &str, function: NativeFn) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_value(Value::Native(Native {
+            name: name.to_string(),
+            function,
+        })))
+    }
+    pub fn from_record_type(value: RecordType) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_value(Value::RecordType(value)))
+    }
+    pub fn from_record(value: Record) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_value(Value::Record(value)))
+    }
+    pub fn from_module(module: Module) -> GenericValue {
+        GenericValue::Object(DynamicSizeObject::from_value(Value::Module(Rc::new(
+            module,
+        ))))
+    }
     pub fn from_object(value: DynamicSizeObject) -> GenericValue {
         GenericValue::Object(value)
     }
@@ -98,14 +560,14 @@ impl Default for GenericValue {
 
 impl Display for GenericValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            GenericValueType::Bool(v) => write!(f, "{}", v),
-            GenericValueType::Number(v) => write!(f, "{}", v),
-            GenericValueType::Nil => write!(f, "nil"),
-            GenericValueType::Object(v) => match v.value.clone() {
-                Value::Str(s) => write!(f, "String<Object>: {}", s),
-            },
+        // what print and string interpolation show, only a string itself
+        // is shown differently from its repr, without quotes
+        if let GenericValueType::Object(o) = self {
+            if let Value::Str(s) = &o.value {
+                return write!(f, "{}", s);
+            }
         }
+        write!(f, "{}", self.repr())
     }
 }
 
@@ -128,13 +590,500 @@ impl GenericValue {
 
     pub fn as_string(&self) -> Option<String> {
         if let GenericValueType::Object(o) = self {
-            let Value::Str(s) = &o.value;
-            Some(s.clone())
-        } else {
-            None
+            if let Value::Str(s) = &o.value {
+                return Some(s.clone());
+            }
+        }
+        None
+    }
+
+    pub fn as_list(&self) -> Option<Vec<GenericValue>> {
+        if let GenericValueType::Object(o) = self {
+            if let Value::List(values) = &o.value {
+                return Some(values.clone());
+            }
+        }
+        None
+    }
+
+    pub fn compare(&self, other: &GenericValue) -> Result<Option<Ordering>, RuntimeError> {
+        /*
+           The ordering behind < <= > >=. Numbers compare numerically,
+           strings and bytes lexicographically, lists and tuples element by
+           element with a shorter prefix first. None means the values are
+           unordered because a nan is involved, any other pair of types is
+           an error.
+        */
+        let cannot_compare = || {
+            RuntimeError::InvalidOperation(format!(
+                "cannot compare {} and {}",
+                self.get_type_as_str(),
+                other.get_type_as_str()
+            ))
+        };
+        let (o1, o2) = match (self, other) {
+            (GenericValueType::Number(n1), GenericValueType::Number(n2)) => {
+                return Ok(n1.partial_cmp(n2))
+            }
+            (GenericValueType::Object(o1), GenericValueType::Object(o2)) => (o1, o2),
+            _ => return Err(cannot_compare()),
+        };
+        match (&o1.value, &o2.value) {
+            (Value::Str(s1), Value::Str(s2)) => Ok(Some(s1.cmp(s2))),
+            (Value::Bytes(b1), Value::Bytes(b2)) => Ok(Some(b1.cmp(b2))),
+            (Value::List(l1), Value::List(l2)) | (Value::Tuple(l1), Value::Tuple(l2)) => {
+                for (v1, v2) in l1.iter().zip(l2.iter()) {
+                    // equal elements are skipped, so [nil, 1] < [nil, 2] works
+                    if v1 == v2 {
+                        continue;
+                    }
+                    return v1.compare(v2);
+                }
+                Ok(Some(l1.len().cmp(&l2.len())))
+            }
+            _ => Err(cannot_compare()),
+        }
+    }
+
+    pub fn check_sortable(values: &[GenericValue]) -> Result<(), RuntimeError> {
+        /*
+           Makes sure compare() orders every pair of the values, without an
+           error or a nan, so sorting by it is a total order. Lists and tuples
+           are checked position by position, and only between the ones whose
+           earlier elements are all equal, the same pairs compare() would
+           look at.
+        */
+        let values: Vec<&GenericValue> = values.iter().collect();
+        check_comparable(&values)
+    }
+
+    pub fn as_list_mut(&mut self) -> Option<&mut Vec<GenericValue>> {
+        if let GenericValueType::Object(o) = self {
+            if let Value::List(values) = &mut o.value {
+                return Some(values);
+            }
+        }
+        None
+    }
+
+    pub fn repr(&self) -> String {
+        /*
+           The debug form of a value, strings are quoted and escaped. The
+           elements of lists, tuples and sets are always shown this way, so
+           ["1", 1] and [1, 1] print differently.
+        */
+        match self {
+            GenericValueType::Bool(v) => v.to_string(),
+            GenericValueType::Number(v) => format_number(*v),
+            GenericValueType::Nil => String::from("nil"),
+            GenericValueType::Object(o) => match &o.value {
+                Value::Str(s) => string_literal(s),
+                Value::List(values) => {
+                    let values: Vec<String> = values.iter().map(|v| v.repr()).collect();
+                    format!("[{}]", values.join(", "))
+                }
+                Value::Tuple(values) => {
+                    let values: Vec<String> = values.iter().map(|v| v.repr()).collect();
+                    format!("({}{})", values.join(", "), trailing_comma(&values))
+                }
+                Value::Range(r) => {
+                    let op = if r.inclusive { "..=" } else { ".." };
+                    format!("{}{}{}", format_number(r.start), op, format_number(r.end))
+                }
+                Value::Iterator(_) => String::from("<iterator>"),
+                Value::Enum(e) => format!("<enum {}>", e.name),
+                Value::Variant(v) => {
+                    if v.fields.is_empty() {
+                        format!("{}.{}", v.enum_name, v.name)
+                    } else {
+                        let fields: Vec<String> = v.fields.iter().map(|v| v.repr()).collect();
+                        format!("{}.{}({})", v.enum_name, v.name, fields.join(", "))
+                    }
+                }
+                Value::Pattern(_) => String::from("<pattern>"),
+                Value::Set(set) => {
+                    let values: Vec<String> =
+                        set.borrow().items().iter().map(|v| v.repr()).collect();
+                    format!("#{{{}}}", values.join(", "))
+                }
+                Value::Native(n) => format!("<native fn {}>", n.name),
+                Value::Bytes(b) => bytes_literal(b),
+                Value::Buffer(b) => format!("buffer({})", bytes_literal(&b.borrow().bytes)),
+                Value::Module(m) => format!("<module {}>", m.name),
+                Value::RecordType(r) => format!("<record {}>", r.name),
+                Value::Record(r) => {
+                    let fields: Vec<String> = r
+                        .fields
+                        .iter()
+                        .zip(&r.values)
+                        .map(|(name, v)| format!("{}: {}", name, v.repr()))
+                        .collect();
+                    format!("{}({})", r.name, fields.join(", "))
+                }
+            },
+        }
+    }
+
+    pub fn is_falsey(&self) -> bool {
+        // nil and false are falsey, every other value is truthy
+        matches!(self, GenericValueType::Nil | GenericValueType::Bool(false))
+    }
+
+    pub fn as_enum(&self) -> Option<&Enum> {
+        if let GenericValueType::Object(o) = self {
+            if let Value::Enum(e) = &o.value {
+                return Some(e);
+            }
+        }
+        None
+    }
+
+    pub fn as_variant(&self) -> Option<&Variant> {
+        if let GenericValueType::Object(o) = self {
+            if let Value::Variant(v) = &o.value {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    pub fn as_pattern(&self) -> Option<&MatchPattern> {
+        if let GenericValueType::Object(o) = self {
+            if let Value::Pattern(p) = &o.value {
+                return Some(p);
+            }
+        }
+        None
+    }
+
+    pub fn as_set(&self) -> Option<Rc<RefCell<ValueSet>>> {
+        if let GenericValueType::Object(o) = self {
+            if let Value::Set(set) = &o.value {
+                return Some(Rc::clone(set));
+            }
+        }
+        None
+    }
+
+    pub fn as_native(&self) -> Option<&Native> {
+        if let GenericValueType::Object(o) = self {
+            if let Value::Native(n) = &o.value {
+                return Some(n);
+            }
+        }
+        None
+    }
+
+    pub fn as_record_type(&self) -> Option<&RecordType> {
+        if let GenericValueType::Object(o) = self {
+            if let Value::RecordType(r) = &o.value {
+                return Some(r);
+            }
+        }
+        None
+    }
+
+    pub fn as_record(&self) -> Option<&Record> {
+        if let GenericValueType::Object(o) = self {
+            if let Value::Record(r) = &o.value {
+                return Some(r);
+            }
+        }
+        None
+    }
+
+    pub fn as_module(&self) -> Option<&Module> {
+        if let GenericValueType::Object(o) = self {
+            if let Value::Module(m) = &o.value {
+                return Some(m);
+            }
+        }
+        None
+    }
+
+    pub fn as_bytes(&self) -> Option<Vec<u8>> {
+        // the contents of either bytes or a buffer
+        if let GenericValueType::Object(o) = self {
+            return o.byte_contents();
+        }
+        None
+    }
+
+    pub fn as_buffer(&self) -> Option<Rc<RefCell<ByteBuffer>>> {
+        if let GenericValueType::Object(o) = self {
+            if let Value::Buffer(b) = &o.value {
+                return Some(Rc::clone(b));
+            }
+        }
+        None
+    }
+
+    pub fn as_byte(&self) -> Result<u8, RuntimeError> {
+        match self.as_number() {
+            Some(n) if n.fract() == 0.0 && (0.0..=255.0).contains(&n) => Ok(n as u8),
+            _ => Err(RuntimeError::InvalidOperation(format!(
+                "expected a byte between 0 and 255 but got {}",
+                self.repr()
+            ))),
+        }
+    }
+
+    pub fn index(&self, index: &GenericValue) -> Result<GenericValue, RuntimeError> {
+        /*
+           value[i] picks a single element, value[a..b] copies a slice.
+           Strings are indexed by code point, bytes and buffers give the
+           byte as a number, and a slice of either is immutable bytes.
+        */
+        let o = match self {
+            GenericValueType::Object(o) => o,
+            _ => return Err(not_indexable(self)),
+        };
+        if let Some(range) = index.as_range() {
+            return match &o.value {
+                Value::List(values) => {
+                    let (start, end) = slice_bounds(range, values.len())?;
+                    Ok(GenericValue::from_list(values[start..end].to_vec()))
+                }
+                Value::Tuple(values) => {
+                    let (start, end) = slice_bounds(range, values.len())?;
+                    Ok(GenericValue::from_tuple(values[start..end].to_vec()))
+                }
+                Value::Str(s) => {
+                    let (start, end) = slice_bounds(range, s.chars().count())?;
+                    Ok(GenericValue::from_string(
+                        s.chars().skip(start).take(end - start).collect(),
+                    ))
+                }
+                Value::Bytes(_) | Value::Buffer(_) => {
+                    let bytes = o.byte_contents().unwrap_or_default();
+                    let (start, end) = slice_bounds(range, bytes.len())?;
+                    Ok(GenericValue::from_bytes(bytes[start..end].to_vec()))
+                }
+                _ => Err(not_indexable(self)),
+            };
+        }
+        match &o.value {
+            Value::List(values) | Value::Tuple(values) => {
+                Ok(values[element_index(index, values.len())?].clone())
+            }
+            Value::Str(s) => {
+                let i = element_index(index, s.chars().count())?;
+                Ok(GenericValue::from_string(
+                    s.chars().nth(i).unwrap_or_default().to_string(),
+                ))
+            }
+            Value::Bytes(b) => Ok(GenericValue::from_f64(
+                b[element_index(index, b.len())?] as f64,
+            )),
+            Value::Buffer(b) => {
+                let b = &b.borrow().bytes;
+                Ok(GenericValue::from_f64(
+                    b[element_index(index, b.len())?] as f64,
+                ))
+            }
+            _ => Err(not_indexable(self)),
+        }
+    }
+
+    pub fn set_index(
+        &self,
+        index: &GenericValue,
+        value: &GenericValue,
+    ) -> Result<(), RuntimeError> {
+        // only buffers can be changed in place, every other value is copied on assignment
+        match self.as_buffer() {
+            Some(b) => {
+                let byte = value.as_byte()?;
+                let mut b = b.borrow_mut();
+                let b = b.bytes_mut()?;
+                let i = element_index(index, b.len())?;
+                b[i] = byte;
+                Ok(())
+            }
+            None => Err(RuntimeError::InvalidOperation(format!(
+                "{} does not support item assignment",
+                self.get_type_as_str()
+            ))),
+        }
+    }
+
+    fn as_range(&self) -> Option<&Range> {
+        if let GenericValueType::Object(o) = self {
+            if let Value::Range(r) = &o.value {
+                return Some(r);
+            }
+        }
+        None
+    }
+
+    pub fn freeze(&self) {
+        // freezes every set and buffer the value holds, however deeply nested
+        if let GenericValueType::Object(o) = self {
+            match &o.value {
+                Value::List(values) | Value::Tuple(values) => {
+                    values.iter().for_each(GenericValue::freeze)
+                }
+                Value::Variant(v) => v.fields.iter().for_each(GenericValue::freeze),
+                Value::Record(r) => r.values.iter().for_each(GenericValue::freeze),
+                Value::Set(set) => set.borrow_mut().freeze(),
+                Value::Buffer(b) => b.borrow_mut().frozen = true,
+                _ => {}
+            }
+        }
+    }
+
+    pub fn check_hashable(&self) -> Result<(), RuntimeError> {
+        /*
+           Only values whose equality can never change may go into a set.
+           Lists and tuples are copied on assignment, so they are fine as
+           long as their elements are, while a set or a buffer is shared and
+           mutable until it is frozen. nan is not even equal to itself, so a
+           set could never find it again.
+        */
+        if let GenericValueType::Number(n) = self {
+            if n.is_nan() {
+                return Err(RuntimeError::InvalidOperation(String::from(
+                    "nan is not hashable",
+                )));
+            }
+        }
+        if let GenericValueType::Object(o) = self {
+            match &o.value {
+                Value::List(values) | Value::Tuple(values) => {
+                    for v in values {
+                        v.check_hashable()?;
+                    }
+                }
+                Value::Variant(v) => {
+                    for field in &v.fields {
+                        field.check_hashable()?;
+                    }
+                }
+                Value::Record(r) => {
+                    for v in &r.values {
+                        v.check_hashable()?;
+                    }
+                }
+                Value::Set(set) if set.borrow().is_frozen() => {}
+                Value::Buffer(b) if b.borrow().frozen => {}
+                Value::Iterator(_)
+                | Value::Pattern(_)
+                | Value::Set(_)
+                | Value::Native(_)
+                | Value::Buffer(_)
+                | Value::Module(_) => {
+                    return Err(RuntimeError::InvalidOperation(format!(
+                        "{} is not hashable",
+                        self.get_type_as_str()
+                    )));
+                }
+                Value::Str(_)
+                | Value::Range(_)
+                | Value::Enum(_)
+                | Value::Bytes(_)
+                | Value::RecordType(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn destructure(
+        &self,
+        count: usize,
+        rest: Option<usize>,
+    ) -> Result<Vec<GenericValue>, RuntimeError> {
+        /*
+           Splits a tuple or list into `count` values for a destructuring
+           pattern. With a rest element at position `rest`, the leftover
+           elements are collected into a value of the same kind there.
+        */
+        let (values, is_tuple) = match self {
+            GenericValueType::Object(o) => match &o.value {
+                Value::List(values) => (values, false),
+                Value::Tuple(values) => (values, true),
+                _ => return Err(cannot_destructure(self)),
+            },
+            _ => return Err(cannot_destructure(self)),
+        };
+        match rest {
+            None if values.len() != count => Err(RuntimeError::InvalidOperation(format!(
+                "expected {} values to unpack but got {}",
+                count,
+                values.len()
+            ))),
+            None => Ok(values.clone()),
+            Some(_) if values.len() + 1 < count => Err(RuntimeError::InvalidOperation(format!(
+                "expected at least {} values to unpack but got {}",
+                count - 1,
+                values.len()
+            ))),
+            Some(before) => {
+                let after = count - before - 1;
+                let rest_end = values.len() - after;
+                let rest_values = values[before..rest_end].to_vec();
+                let mut output = values[..before].to_vec();
+                output.push(if is_tuple {
+                    GenericValue::from_tuple(rest_values)
+                } else {
+                    GenericValue::from_list(rest_values)
+                });
+                output.extend_from_slice(&values[rest_end..]);
+                Ok(output)
+            }
+        }
+    }
+
+    pub fn iter(&self) -> Result<GenericValue, RuntimeError> {
+        // turns an iterable into the iterator driving a for-in loop
+        let iterator = match self {
+            GenericValueType::Object(o) => match &o.value {
+                Value::Range(r) => Some(ValueIterator::Range {
+                    next: r.start,
+                    end: r.end,
+                    inclusive: r.inclusive,
+                }),
+                Value::Str(s) => Some(ValueIterator::Str {
+                    s: s.clone(),
+                    offset: 0,
+                }),
+                Value::List(values) | Value::Tuple(values) => Some(ValueIterator::List {
+                    values: values.clone(),
+                    index: 0,
+                }),
+                // a snapshot, adding to the set inside the loop does not affect it
+                Value::Set(set) => Some(ValueIterator::List {
+                    values: set.borrow().items().to_vec(),
+                    index: 0,
+                }),
+                Value::Bytes(_) | Value::Buffer(_) => Some(ValueIterator::List {
+                    values: byte_numbers(&o.byte_contents().unwrap_or_default()),
+                    index: 0,
+                }),
+                _ => None,
+            },
+            _ => None,
+        };
+        match iterator {
+            Some(iterator) => Ok(GenericValue::from_object(DynamicSizeObject::from_iterator(
+                iterator,
+            ))),
+            None => Err(RuntimeError::InvalidOperation(format!(
+                "{} is not iterable",
+                self.get_type_as_str()
+            ))),
         }
     }
 
+    pub fn iter_next(&mut self) -> Option<GenericValue> {
+        // None once the iterator is exhausted
+        if let GenericValueType::Object(o) = self {
+            if let Value::Iterator(iterator) = &mut o.value {
+                return iterator.advance();
+            }
+        }
+        None
+    }
+
     pub fn as_object(&self) -> Option<DynamicSizeObject> {
         if let GenericValueType::Object(o) = self {
             Some(o.clone())
@@ -144,6 +1093,120 @@ impl GenericValue {
     }
 }
 
+fn trailing_comma(values: &[String]) -> &'static str {
+    // a one element tuple prints as (a,)
+    if values.len() == 1 {
+        ","
+    } else {
+        ""
+    }
+}
+
+fn not_indexable(value: &GenericValue) -> RuntimeError {
+    RuntimeError::InvalidOperation(format!("{} is not indexable", value.get_type_as_str()))
+}
+
+fn element_index(index: &GenericValue, len: usize) -> Result<usize, RuntimeError> {
+    match index.as_number() {
+        Some(n) if n.fract() == 0.0 && n >= 0.0 && (n as usize) < len => Ok(n as usize),
+        Some(n) if n.fract() == 0.0 => Err(RuntimeError::InvalidOperation(format!(
+            "index {} out of range for length {}",
+            n, len
+        ))),
+        _ => Err(RuntimeError::InvalidOperation(format!(
+            "index must be an integer or a range but got {}",
+            index.get_type_as_str()
+        ))),
+    }
+}
+
+fn slice_bounds(range: &Range, len: usize) -> Result<(usize, usize), RuntimeError> {
+    let end = if range.inclusive {
+        range.end + 1.0
+    } else {
+        range.end
+    };
+    if range.start.fract() != 0.0
+        || range.end.fract() != 0.0
+        || range.start < 0.0
+        || range.start > end
+        || end > len as f64
+    {
+        return Err(RuntimeError::InvalidOperation(format!(
+            "slice {} out of range for length {}",
+            GenericValue::from_range(range.start, range.end, range.inclusive),
+            len
+        )));
+    }
+    Ok((range.start as usize, end as usize))
+}
+
+fn byte_numbers(bytes: &[u8]) -> Vec<GenericValue> {
+    bytes
+        .iter()
+        .map(|b| GenericValue::from_f64(*b as f64))
+        .collect()
+}
+
+pub fn format_number(n: f64) -> String {
+    /*
+       Integers print without a fractional part and every other number in
+       the shortest form that reads back as the same value. Very large and
+       very small magnitudes switch to exponent notation, 1e21 and 1e-7.
+    */
+    if n.is_nan() {
+        return String::from("nan");
+    }
+    if n.is_infinite() {
+        return String::from(if n > 0.0 { "inf" } else { "-inf" });
+    }
+    let magnitude = n.abs();
+    if magnitude >= 1e21 || (magnitude != 0.0 && magnitude < 1e-6) {
+        format!("{:e}", n)
+    } else {
+        n.to_string()
+    }
+}
+
+fn string_literal(s: &str) -> String {
+    let mut output = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c.is_control() => output.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+fn bytes_literal(bytes: &[u8]) -> String {
+    // b"..." with every byte that is not printable ASCII escaped
+    let mut output = String::from("b\"");
+    for b in bytes {
+        match b {
+            b'"' => output.push_str("\\\""),
+            b'\\' => output.push_str("\\\\"),
+            b'\n' => output.push_str("\\n"),
+            b'\r' => output.push_str("\\r"),
+            b'\t' => output.push_str("\\t"),
+            0x20..=0x7e => output.push(*b as char),
+            _ => output.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    output.push('"');
+    output
+}
+
+fn cannot_destructure(value: &GenericValue) -> RuntimeError {
+    RuntimeError::InvalidOperation(format!("cannot destructure {}", value.get_type_as_str()))
+}
+
 impl Add for GenericValue {
     type Output = Result<GenericValueType, RuntimeError>; // Should be using Result, and define an error for compiler error to handler
     fn add(self, other: GenericValue) -> Result<Self, RuntimeError> {
@@ -171,6 +1234,44 @@ impl Sub for GenericValue {
             (GenericValueType::Number(lhs), GenericValueType::Number(rhs)) => {
                 Ok(GenericValue::from_f64(lhs - rhs))
             }
+            _ => match (self.as_set(), other.as_set()) {
+                // set difference
+                (Some(s1), Some(s2)) => {
+                    Ok(GenericValue::from_set(s1.borrow().difference(&s2.borrow())))
+                }
+                _ => Err(RuntimeError::UnsupportedOperation(
+                    self.get_type_as_str(),
+                    other.get_type_as_str(),
+                )),
+            },
+        }
+    }
+}
+
+impl BitOr for GenericValue {
+    type Output = Result<GenericValueType, RuntimeError>;
+
+    fn bitor(self, other: GenericValueType) -> Result<Self, RuntimeError> {
+        // set union
+        match (self.as_set(), other.as_set()) {
+            (Some(s1), Some(s2)) => Ok(GenericValue::from_set(s1.borrow().union(&s2.borrow()))),
+            _ => Err(RuntimeError::UnsupportedOperation(
+                self.get_type_as_str(),
+                other.get_type_as_str(),
+            )),
+        }
+    }
+}
+
+impl BitAnd for GenericValue {
+    type Output = Result<GenericValueType, RuntimeError>;
+
+    fn bitand(self, other: GenericValueType) -> Result<Self, RuntimeError> {
+        // set intersection
+        match (self.as_set(), other.as_set()) {
+            (Some(s1), Some(s2)) => Ok(GenericValue::from_set(
+                s1.borrow().intersection(&s2.borrow()),
+            )),
             _ => Err(RuntimeError::UnsupportedOperation(
                 self.get_type_as_str(),
                 other.get_type_as_str(),
@@ -233,10 +1334,15 @@ impl Neg for GenericValue {
 
 impl PartialEq for GenericValue {
     fn eq(&self, other: &Self) -> bool {
+        /*
+           Values of different types are never equal, 1 == "1" is false.
+           Numbers follow IEEE 754, so nan is not even equal to itself.
+        */
         match (self, other) {
             (GenericValueType::Number(v1), GenericValueType::Number(v2)) => v1 == v2,
             (GenericValueType::Bool(b1), GenericValueType::Bool(b2)) => b1 == b2,
             (GenericValueType::Object(o1), GenericValueType::Object(o2)) => o1 == o2,
+            (GenericValueType::Nil, GenericValueType::Nil) => true,
             _ => false,
         }
     }
@@ -244,6 +1350,18 @@ impl PartialEq for GenericValue {
 
 impl Eq for GenericValue {}
 
+impl Hash for GenericValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            GenericValueType::Bool(b) => b.hash(state),
+            GenericValueType::Number(n) => hash_f64(*n, state),
+            GenericValueType::Object(o) => o.hash(state),
+            GenericValueType::Nil => {}
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct ValueArray {
     pub values: Vec<GenericValue>,
@@ -1,10 +1,14 @@
 use crate::chunk::Chunk;
 use crate::constants;
 use crate::errors::runtime_error;
-use crate::table;
+use crate::methods;
+use crate::modules::Modules;
+use crate::natives::define_natives;
+use crate::set::ValueSet;
 use crate::table::Table;
 use crate::values::GenericValue;
 use crate::values::GenericValueType;
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -30,6 +34,35 @@ pub enum OpCode {
     OpDefineGlobal,
     OpGetGlobal,
     OpSetGlobal,
+    OpToString,
+    OpBuildList,
+    OpInvoke,
+    OpRange,
+    OpIterInit,
+    OpIterNext,
+    OpLoop,
+    OpBuildTuple,
+    OpUnpack,
+    OpUnpackRest,
+    OpGetProperty,
+    OpJump,
+    OpJumpIfFalse,
+    OpMatch,
+    OpNoMatch,
+    OpBuildSet,
+    OpBitOr,
+    OpBitAnd,
+    OpCall,
+    OpIndex,
+    OpSetIndex,
+    OpJumpIfNil,
+    OpSwap,
+    OpListAppend,
+    OpListExtend,
+    OpCallList,
+    OpInvokeList,
+    OpImport,
+    OpImportName,
     // TODO: implement bang equal, mod %
 }
 
@@ -57,6 +90,35 @@ impl OpCode {
             19 => OpCode::OpDefineGlobal,
             20 => OpCode::OpGetGlobal,
             21 => OpCode::OpSetGlobal,
+            22 => OpCode::OpToString,
+            23 => OpCode::OpBuildList,
+            24 => OpCode::OpInvoke,
+            25 => OpCode::OpRange,
+            26 => OpCode::OpIterInit,
+            27 => OpCode::OpIterNext,
+            28 => OpCode::OpLoop,
+            29 => OpCode::OpBuildTuple,
+            30 => OpCode::OpUnpack,
+            31 => OpCode::OpUnpackRest,
+            32 => OpCode::OpGetProperty,
+            33 => OpCode::OpJump,
+            34 => OpCode::OpJumpIfFalse,
+            35 => OpCode::OpMatch,
+            36 => OpCode::OpNoMatch,
+            37 => OpCode::OpBuildSet,
+            38 => OpCode::OpBitOr,
+            39 => OpCode::OpBitAnd,
+            40 => OpCode::OpCall,
+            41 => OpCode::OpIndex,
+            42 => OpCode::OpSetIndex,
+            43 => OpCode::OpJumpIfNil,
+            44 => OpCode::OpSwap,
+            45 => OpCode::OpListAppend,
+            46 => OpCode::OpListExtend,
+            47 => OpCode::OpCallList,
+            48 => OpCode::OpInvokeList,
+            49 => OpCode::OpImport,
+            50 => OpCode::OpImportName,
             _ => panic!("Unknown value: {}", value),
         }
     }
@@ -86,6 +148,35 @@ impl Display for OpCode {
             Self::OpDefineGlobal => "OpDefineGlobal",
             Self::OpGetGlobal => "OpGetGlobal",
             Self::OpSetGlobal => "OpSetGlobal",
+            Self::OpToString => "OpToString",
+            Self::OpBuildList => "OpBuildList",
+            Self::OpInvoke => "OpInvoke",
+            Self::OpRange => "OpRange",
+            Self::OpIterInit => "OpIterInit",
+            Self::OpIterNext => "OpIterNext",
+            Self::OpLoop => "OpLoop",
+            Self::OpBuildTuple => "OpBuildTuple",
+            Self::OpUnpack => "OpUnpack",
+            Self::OpUnpackRest => "OpUnpackRest",
+            Self::OpGetProperty => "OpGetProperty",
+            Self::OpJump => "OpJump",
+            Self::OpJumpIfFalse => "OpJumpIfFalse",
+            Self::OpMatch => "OpMatch",
+            Self::OpNoMatch => "OpNoMatch",
+            Self::OpBuildSet => "OpBuildSet",
+            Self::OpBitOr => "OpBitOr",
+            Self::OpBitAnd => "OpBitAnd",
+            Self::OpCall => "OpCall",
+            Self::OpIndex => "OpIndex",
+            Self::OpSetIndex => "OpSetIndex",
+            Self::OpJumpIfNil => "OpJumpIfNil",
+            Self::OpSwap => "OpSwap",
+            Self::OpListAppend => "OpListAppend",
+            Self::OpListExtend => "OpListExtend",
+            Self::OpCallList => "OpCallList",
+            Self::OpInvokeList => "OpInvokeList",
+            Self::OpImport => "OpImport",
+            Self::OpImportName => "OpImportName",
         };
         write!(f, "{}", s)
     }
@@ -95,6 +186,8 @@ impl Display for OpCode {
 pub enum RuntimeError {
     UnsupportedOperation(String, String),
     InvalidOperation(String),
+    UndefinedMethod(String, String),
+    UndefinedProperty(String, String),
 }
 
 impl Display for RuntimeError {
@@ -104,6 +197,12 @@ impl Display for RuntimeError {
                 write!(f, "Operation not supported for {} and {}", type1, type2)
             }
             RuntimeError::InvalidOperation(msg) => write!(f, "Invalid operation: {}", msg),
+            RuntimeError::UndefinedMethod(type_name, name) => {
+                write!(f, "Undefined method '{}' for {}", name, type_name)
+            }
+            RuntimeError::UndefinedProperty(type_name, name) => {
+                write!(f, "Undefined property '{}' for {}", name, type_name)
+            }
         }
     }
 }
@@ -115,20 +214,28 @@ pub enum InterpretResult {
     InterpretRunTimeError,
 }
 
-#[derive(Default)]
-
 pub struct VirtualMachine {
     pub ip: usize, // instruction pointer, the index currently pointing to the instruction in chunk
     pub vm_stack: VirtualMachineStack,
     pub table: Table,
+    pub modules: Modules,
+}
+
+impl Default for VirtualMachine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl VirtualMachine {
     pub fn new() -> Self {
+        let mut table = Table::default();
+        define_natives(&mut table);
         VirtualMachine {
             ip: 0,
             vm_stack: VirtualMachineStack::default(),
-            table: Table::default(),
+            table,
+            modules: Modules::default(),
         }
     }
     pub fn run(&mut self, chunk: &mut Chunk) -> InterpretResult {
@@ -136,7 +243,7 @@ impl VirtualMachine {
             #[cfg(debug_assertions)]
             {
                 for i in 0..self.vm_stack.ptr {
-                    print!("[ {} ]", self.vm_stack.values[i])
+                    print!("[ {} ]", self.vm_stack.values[i].repr())
                 }
                 println!();
                 disassemble_instruction(chunk, self.ip);
@@ -151,169 +258,349 @@ impl VirtualMachine {
                     self.vm_stack.push(val);
                 }
                 OpCode::OpNegate => {
-                    self.vm_stack.negate_peek();
+                    if let Err(e) = self.vm_stack.negate_peek() {
+                        runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                        return InterpretResult::InterpretRunTimeError;
+                    }
                 }
-                OpCode::OpAdd => {
-                    let v1 = self.vm_stack.pop();
-                    let v2 = self.vm_stack.pop(); // Handle empty value stack
-
-                    let v = v1 + v2;
-
+                OpCode::OpAdd
+                | OpCode::OpSubtract
+                | OpCode::OpMultiply
+                | OpCode::OpDivide
+                | OpCode::OpBitOr
+                | OpCode::OpBitAnd => {
+                    /*
+                       The arithmetic and bitwise operators, dispatched on the
+                       operator traits GenericValue implements. Equality,
+                       comparisons and ranges have their own arms.
+                    */
+                    let v2 = self.vm_stack.pop();
+                    let v1 = self.vm_stack.pop(); // Handle empty value stack
+                    let v = match op_code {
+                        OpCode::OpAdd => v1 + v2,
+                        OpCode::OpSubtract => v1 - v2,
+                        OpCode::OpMultiply => v1 * v2,
+                        OpCode::OpDivide => v1 / v2,
+                        OpCode::OpBitOr => v1 | v2,
+                        _ => v1 & v2,
+                    };
                     match v {
-                        // TODO: put the actual line, not 0
-                        Ok(v) => {
-                            self.vm_stack.push(v);
+                        Ok(v) => self.vm_stack.push(v),
+                        Err(e) => {
+                            runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                            return InterpretResult::InterpretRunTimeError;
                         }
-                        Err(e) => runtime_error(0, e.to_string().as_str()),
                     }
                 }
-                OpCode::OpSubtract => {
+                OpCode::OpNil => self.vm_stack.push(GenericValue::from_none()),
+                OpCode::OpFalse => self.vm_stack.push(GenericValue::from_bool(false)),
+                OpCode::OpTrue => self.vm_stack.push(GenericValue::from_bool(true)),
+                OpCode::OpNot => {
+                    let val = self.vm_stack.pop();
+                    self.vm_stack.push(GenericValue::from_bool(val.is_falsey()));
+                }
+                OpCode::OpEqual => {
+                    let v2 = self.vm_stack.pop();
                     let v1 = self.vm_stack.pop();
-                    let v2 = self.vm_stack.pop(); // Handle empty value stack
-                    let v = v1 - v2;
-                    match v {
-                        Ok(v) => self.vm_stack.push(v),
-                        Err(e) => runtime_error(0, e.to_string().as_str()),
-                    }
+                    self.vm_stack.push(GenericValue::from_bool(v1 == v2))
                 }
-                OpCode::OpMultiply => {
+                OpCode::OpGreater
+                | OpCode::OpLess
+                | OpCode::OpGreaterEqual
+                | OpCode::OpLessEqual => {
+                    // None is an unordered comparison involving nan, always false
+                    let v2 = self.vm_stack.pop();
                     let v1 = self.vm_stack.pop();
-                    let v2 = self.vm_stack.pop(); // Handle empty value stack
-                    let v = v1 * v2;
-                    match v {
-                        // TODO: put the actual line, not 0
+                    let ordering = match v1.compare(&v2) {
+                        Ok(ordering) => ordering,
+                        Err(e) => {
+                            runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                            return InterpretResult::InterpretRunTimeError;
+                        }
+                    };
+                    let result = match op_code {
+                        OpCode::OpGreater => ordering.is_some_and(Ordering::is_gt),
+                        OpCode::OpLess => ordering.is_some_and(Ordering::is_lt),
+                        OpCode::OpGreaterEqual => ordering.is_some_and(Ordering::is_ge),
+                        _ => ordering.is_some_and(Ordering::is_le),
+                    };
+                    self.vm_stack.push(GenericValue::from_bool(result));
+                }
+                OpCode::OpPrint => {
+                    println!("{}", self.vm_stack.pop()) // 我手改成 peek , 書裡面寫 pop, 我在思考....
+                }
+                OpCode::OpPop => {
+                    self.vm_stack.pop();
+                }
+                OpCode::OpToString => {
+                    let val = self.vm_stack.pop();
+                    self.vm_stack
+                        .push(GenericValue::from_string(val.to_string()));
+                }
+                OpCode::OpBuildList => {
+                    let count = self.read_op_raw(chunk);
+                    let mut values = vec![GenericValue::default(); count];
+                    for i in (0..count).rev() {
+                        values[i] = self.vm_stack.pop();
+                    }
+                    self.vm_stack.push(GenericValue::from_list(values));
+                }
+                OpCode::OpInvoke | OpCode::OpInvokeList => {
+                    let name = self.read_string(chunk);
+                    let args = if op_code == OpCode::OpInvoke {
+                        let arg_count = self.read_op_raw(chunk);
+                        let mut args = vec![GenericValue::default(); arg_count];
+                        for i in (0..arg_count).rev() {
+                            args[i] = self.vm_stack.pop();
+                        }
+                        args
+                    } else {
+                        self.vm_stack.pop().as_list().unwrap_or_default()
+                    };
+                    let receiver = self.vm_stack.pop();
+                    match methods::invoke(&receiver, &name, &args) {
                         Ok(v) => self.vm_stack.push(v),
-                        Err(e) => runtime_error(0, e.to_string().as_str()),
+                        Err(e) => {
+                            runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                            return InterpretResult::InterpretRunTimeError;
+                        }
                     }
                 }
-                OpCode::OpDivide => {
-                    let v1 = self.vm_stack.pop();
-                    let v2 = self.vm_stack.pop(); // Handle empty value stack
-                    let v = v1 / v2;
-                    match v {
-                        // TODO: put the actual line, not 0
+                OpCode::OpBuildSet => {
+                    let count = self.read_op_raw(chunk);
+                    let mut values = vec![GenericValue::default(); count];
+                    for i in (0..count).rev() {
+                        values[i] = self.vm_stack.pop();
+                    }
+                    let mut set = ValueSet::new();
+                    for v in values {
+                        if let Err(e) = set.add(v) {
+                            runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                            return InterpretResult::InterpretRunTimeError;
+                        }
+                    }
+                    self.vm_stack.push(GenericValue::from_set(set));
+                }
+                OpCode::OpCall | OpCode::OpCallList => {
+                    let args = if op_code == OpCode::OpCall {
+                        let arg_count = self.read_op_raw(chunk);
+                        let mut args = vec![GenericValue::default(); arg_count];
+                        for i in (0..arg_count).rev() {
+                            args[i] = self.vm_stack.pop();
+                        }
+                        args
+                    } else {
+                        // the arguments were collected into a list because of a spread
+                        self.vm_stack.pop().as_list().unwrap_or_default()
+                    };
+                    let callee = self.vm_stack.pop();
+                    match methods::call(&callee, &args) {
                         Ok(v) => self.vm_stack.push(v),
-                        Err(e) => runtime_error(0, e.to_string().as_str()),
+                        Err(e) => {
+                            runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                            return InterpretResult::InterpretRunTimeError;
+                        }
                     }
                 }
-                OpCode::OpNil => self.vm_stack.push(GenericValue::from_none()),
-                OpCode::OpFalse => self.vm_stack.push(GenericValue::from_bool(true)),
-                OpCode::OpTrue => self.vm_stack.push(GenericValue::from_bool(false)),
-                OpCode::OpNot => {
-                    let val = self.vm_stack.pop();
-
-                    // TODO: move this to value, operator overloading (trait ~~~)
-                    fn is_false(v: &GenericValue) -> Result<bool, RuntimeError> {
-                        match v {
-                            GenericValueType::Nil => Ok(true),
-                            GenericValueType::Bool(b) => Ok(!b),
-                            _ => Err(RuntimeError::InvalidOperation("unary only support boolean and None, should the error be implemented in this phase ?".to_string())),
+                OpCode::OpIndex => {
+                    let index = self.vm_stack.pop();
+                    let target = self.vm_stack.pop();
+                    match target.index(&index) {
+                        Ok(v) => self.vm_stack.push(v),
+                        Err(e) => {
+                            runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                            return InterpretResult::InterpretRunTimeError;
                         }
                     }
-                    match is_false(&val) {
-                        Ok(v) => self.vm_stack.push(GenericValue::from_bool(v)),
-                        Err(e) => runtime_error(0, e.to_string().as_str()),
+                }
+                OpCode::OpSetIndex => {
+                    // like OpSetGlobal the assigned value stays on the stack
+                    let value = self.vm_stack.pop();
+                    let index = self.vm_stack.pop();
+                    let target = self.vm_stack.pop();
+                    if let Err(e) = target.set_index(&index, &value) {
+                        runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                        return InterpretResult::InterpretRunTimeError;
                     }
+                    self.vm_stack.push(value);
                 }
-                OpCode::OpEqual => {
-                    let v1 = self.vm_stack.pop();
-                    let v2 = self.vm_stack.pop();
-                    self.vm_stack.push(GenericValue::from_bool(v1 == v2))
+                OpCode::OpBuildTuple => {
+                    let count = self.read_op_raw(chunk);
+                    let mut values = vec![GenericValue::default(); count];
+                    for i in (0..count).rev() {
+                        values[i] = self.vm_stack.pop();
+                    }
+                    self.vm_stack.push(GenericValue::from_tuple(values));
                 }
-                OpCode::OpGreater => {
-                    let v1 = self.vm_stack.pop();
-                    let v2 = self.vm_stack.pop();
-
-                    // TODO: move this to value, operator overloading (trait ~~~)
-                    fn is_greater(
-                        v1: GenericValue,
-                        v2: GenericValue,
-                    ) -> Result<bool, RuntimeError> {
-                        match (v1, v2) {
-                            (GenericValueType::Number(n1), GenericValueType::Number(n2)) => {
-                                Ok(n1 > n2)
+                OpCode::OpUnpack | OpCode::OpUnpackRest => {
+                    let (count, rest) = if op_code == OpCode::OpUnpack {
+                        (self.read_op_raw(chunk), None)
+                    } else {
+                        let before = self.read_op_raw(chunk);
+                        let after = self.read_op_raw(chunk);
+                        (before + 1 + after, Some(before))
+                    };
+                    let value = self.vm_stack.pop();
+                    match value.destructure(count, rest) {
+                        Ok(values) => {
+                            for v in values {
+                                self.vm_stack.push(v);
                             }
-                            _ => Err(RuntimeError::InvalidOperation(
-                                " > not supported ".to_string(),
-                            )),
+                        }
+                        Err(e) => {
+                            runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                            return InterpretResult::InterpretRunTimeError;
                         }
                     }
-                    match is_greater(v1, v2) {
-                        Err(e) => runtime_error(0, e.to_string().as_str()),
-                        Ok(v) => self.vm_stack.push(GenericValueType::from_bool(v)),
+                }
+                OpCode::OpGetProperty => {
+                    let name = self.read_string(chunk);
+                    let receiver = self.vm_stack.pop();
+                    match methods::get_property(&receiver, &name) {
+                        Ok(v) => self.vm_stack.push(v),
+                        Err(e) => {
+                            runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                            return InterpretResult::InterpretRunTimeError;
+                        }
                     }
                 }
-                OpCode::OpLess => {
-                    let v1 = self.vm_stack.pop();
-                    let v2 = self.vm_stack.pop();
-
-                    // TODO: move this to value, operator overloading (trait ~~~)
-                    fn is_less(v1: GenericValue, v2: GenericValue) -> Result<bool, RuntimeError> {
-                        match (v1, v2) {
-                            (GenericValueType::Number(n1), GenericValueType::Number(n2)) => {
-                                Ok(n1 < n2)
-                            }
-                            _ => Err(RuntimeError::InvalidOperation(
-                                " < not supported ".to_string(),
-                            )),
+                OpCode::OpImport => {
+                    // the module of the file at the path, run the first time it is imported
+                    let path = self.read_string(chunk);
+                    match self.modules.import(&path) {
+                        Ok(module) => self.vm_stack.push(module),
+                        Err(e) => {
+                            runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                            return InterpretResult::InterpretRunTimeError;
                         }
                     }
-                    match is_less(v1, v2) {
-                        Err(e) => runtime_error(0, e.to_string().as_str()),
-                        Ok(v) => self.vm_stack.push(GenericValueType::from_bool(v)),
+                }
+                OpCode::OpImportName => {
+                    // `from "x" import a`, the module stays on the stack for the next name
+                    let name = self.read_string(chunk);
+                    match methods::get_property(&self.vm_stack.peek(0), &name) {
+                        Ok(v) => self.vm_stack.push(v),
+                        Err(e) => {
+                            runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                            return InterpretResult::InterpretRunTimeError;
+                        }
                     }
                 }
-                OpCode::OpGreaterEqual => {
-                    let v1 = self.vm_stack.pop();
-                    let v2 = self.vm_stack.pop();
-
-                    // TODO: move this to value, operator overloading (trait ~~~)
-                    fn is_greater_equal(
-                        v1: GenericValue,
-                        v2: GenericValue,
-                    ) -> Result<bool, RuntimeError> {
-                        match (v1, v2) {
-                            (GenericValueType::Number(n1), GenericValueType::Number(n2)) => {
-                                Ok(n1 >= n2)
-                            }
-                            _ => Err(RuntimeError::InvalidOperation(
-                                " >= not supported ".to_string(),
-                            )),
+                OpCode::OpJump => {
+                    let offset = self.read_op_raw(chunk);
+                    self.ip += offset;
+                }
+                OpCode::OpJumpIfFalse => {
+                    // the condition stays on the stack
+                    let offset = self.read_op_raw(chunk);
+                    if self.vm_stack.peek(0).is_falsey() {
+                        self.ip += offset;
+                    }
+                }
+                OpCode::OpListAppend => {
+                    let value = self.vm_stack.pop();
+                    if let Some(values) = self.vm_stack.peek_mut(0).as_list_mut() {
+                        values.push(value);
+                    }
+                }
+                OpCode::OpListExtend => {
+                    // ...value, anything iterable can be spread
+                    let spread = self.vm_stack.pop();
+                    let mut iterator = match spread.iter() {
+                        Ok(iterator) => iterator,
+                        Err(_) => {
+                            runtime_error(
+                                chunk.lines[self.ip - 1],
+                                &format!("cannot spread {}", spread.get_type_as_str()),
+                            );
+                            return InterpretResult::InterpretRunTimeError;
+                        }
+                    };
+                    if let Some(values) = self.vm_stack.peek_mut(0).as_list_mut() {
+                        while let Some(v) = iterator.iter_next() {
+                            values.push(v);
                         }
                     }
-                    match is_greater_equal(v1, v2) {
-                        Err(e) => runtime_error(0, e.to_string().as_str()),
-                        Ok(v) => self.vm_stack.push(GenericValueType::from_bool(v)),
+                }
+                OpCode::OpSwap => {
+                    let top = self.vm_stack.pop();
+                    let below = self.vm_stack.pop();
+                    self.vm_stack.push(top);
+                    self.vm_stack.push(below);
+                }
+                OpCode::OpJumpIfNil => {
+                    // like OpJumpIfFalse the value stays on the stack
+                    let offset = self.read_op_raw(chunk);
+                    if matches!(self.vm_stack.peek(0), GenericValueType::Nil) {
+                        self.ip += offset;
                     }
                 }
-                OpCode::OpLessEqual => {
-                    let v1 = self.vm_stack.pop();
-                    let v2 = self.vm_stack.pop();
-
-                    // TODO: move this to value, operator overloading (trait ~~~)
-                    fn is_less_equal(
-                        v1: GenericValue,
-                        v2: GenericValue,
-                    ) -> Result<bool, RuntimeError> {
-                        match (v1, v2) {
-                            (GenericValueType::Number(n1), GenericValueType::Number(n2)) => {
-                                Ok(n1 <= n2)
-                            }
-                            _ => Err(RuntimeError::InvalidOperation(
-                                " <= not supported ".to_string(),
-                            )),
+                OpCode::OpMatch => {
+                    // on a match the bound values are pushed above the scrutinee
+                    let pattern = self.read_constant(chunk);
+                    let offset = self.read_op_raw(chunk);
+                    let pattern = pattern
+                        .as_pattern()
+                        .expect("OpMatch operand should always be a pattern");
+                    let mut bindings = Vec::new();
+                    if pattern.matches(&self.vm_stack.peek(0), &mut bindings) {
+                        for v in bindings {
+                            self.vm_stack.push(v);
+                        }
+                    } else {
+                        self.ip += offset;
+                    }
+                }
+                OpCode::OpNoMatch => {
+                    let value = self.vm_stack.pop();
+                    runtime_error(
+                        chunk.lines[self.ip - 1],
+                        &format!("No match arm matches value {}", value.repr()),
+                    );
+                    return InterpretResult::InterpretRunTimeError;
+                }
+                OpCode::OpRange => {
+                    let inclusive = self.read_op_raw(chunk) == 1;
+                    let end = self.vm_stack.pop();
+                    let start = self.vm_stack.pop();
+                    match (start.as_number(), end.as_number()) {
+                        (Some(start), Some(end)) => self
+                            .vm_stack
+                            .push(GenericValue::from_range(start, end, inclusive)),
+                        _ => {
+                            runtime_error(
+                                chunk.lines[self.ip - 1],
+                                &RuntimeError::UnsupportedOperation(
+                                    start.get_type_as_str(),
+                                    end.get_type_as_str(),
+                                )
+                                .to_string(),
+                            );
+                            return InterpretResult::InterpretRunTimeError;
                         }
                     }
-                    match is_less_equal(v1, v2) {
-                        Err(e) => runtime_error(0, e.to_string().as_str()),
-                        Ok(v) => self.vm_stack.push(GenericValueType::from_bool(v)),
+                }
+                OpCode::OpIterInit => {
+                    let iterable = self.vm_stack.pop();
+                    match iterable.iter() {
+                        Ok(iterator) => self.vm_stack.push(iterator),
+                        Err(e) => {
+                            runtime_error(chunk.lines[self.ip - 1], e.to_string().as_str());
+                            return InterpretResult::InterpretRunTimeError;
+                        }
                     }
                 }
-                OpCode::OpPrint => {
-                    println!("{}", self.vm_stack.pop()) // 我手改成 peek , 書裡面寫 pop, 我在思考....
+                OpCode::OpIterNext => {
+                    // the iterator stays on the stack, the exhausted case jumps out of the loop
+                    let offset = self.read_op_raw(chunk);
+                    match self.vm_stack.peek_mut(0).iter_next() {
+                        Some(v) => self.vm_stack.push(v),
+                        None => self.ip += offset,
+                    }
                 }
-                OpCode::OpPop => {
-                    self.vm_stack.pop();
+                OpCode::OpLoop => {
+                    let offset = self.read_op_raw(chunk);
+                    self.ip -= offset;
                 }
                 OpCode::OpDefineGlobal => {
                     let name = self.read_string(chunk);
@@ -407,6 +694,35 @@ pub fn disassemble_instruction(chunk: &Chunk, offset: usize) -> usize {
         OpCode::OpDefineGlobal => constant_instruction(OpCode::OpDefineGlobal, offset, chunk),
         OpCode::OpGetGlobal => constant_instruction(OpCode::OpGetGlobal, offset, chunk),
         OpCode::OpSetGlobal => constant_instruction(OpCode::OpSetGlobal, offset, chunk),
+        OpCode::OpToString => simple_instruction(instruction, offset),
+        OpCode::OpBuildList => byte_instruction(instruction, offset, chunk),
+        OpCode::OpInvoke => invoke_instruction(instruction, offset, chunk),
+        OpCode::OpRange => byte_instruction(instruction, offset, chunk),
+        OpCode::OpIterInit => simple_instruction(instruction, offset),
+        OpCode::OpIterNext => jump_instruction(instruction, true, offset, chunk),
+        OpCode::OpLoop => jump_instruction(instruction, false, offset, chunk),
+        OpCode::OpBuildTuple => byte_instruction(instruction, offset, chunk),
+        OpCode::OpUnpack => byte_instruction(instruction, offset, chunk),
+        OpCode::OpUnpackRest => unpack_rest_instruction(instruction, offset, chunk),
+        OpCode::OpGetProperty => constant_instruction(instruction, offset, chunk),
+        OpCode::OpJump => jump_instruction(instruction, true, offset, chunk),
+        OpCode::OpJumpIfFalse => jump_instruction(instruction, true, offset, chunk),
+        OpCode::OpMatch => match_instruction(instruction, offset, chunk),
+        OpCode::OpNoMatch => simple_instruction(instruction, offset),
+        OpCode::OpBuildSet => byte_instruction(instruction, offset, chunk),
+        OpCode::OpBitOr => simple_instruction(instruction, offset),
+        OpCode::OpBitAnd => simple_instruction(instruction, offset),
+        OpCode::OpCall => byte_instruction(instruction, offset, chunk),
+        OpCode::OpIndex => simple_instruction(instruction, offset),
+        OpCode::OpSetIndex => simple_instruction(instruction, offset),
+        OpCode::OpJumpIfNil => jump_instruction(instruction, true, offset, chunk),
+        OpCode::OpSwap => simple_instruction(instruction, offset),
+        OpCode::OpListAppend => simple_instruction(instruction, offset),
+        OpCode::OpListExtend => simple_instruction(instruction, offset),
+        OpCode::OpCallList => simple_instruction(instruction, offset),
+        OpCode::OpInvokeList => constant_instruction(instruction, offset, chunk),
+        OpCode::OpImport => constant_instruction(instruction, offset, chunk),
+        OpCode::OpImportName => constant_instruction(instruction, offset, chunk),
     }
 }
 
@@ -415,6 +731,52 @@ pub fn simple_instruction(op: OpCode, offset: usize) -> usize {
     offset + 1
 }
 
+pub fn byte_instruction(op: OpCode, offset: usize, chunk: &Chunk) -> usize {
+    let operand = chunk.bytecode[offset + 1];
+    println!("{}{}{}", op, " ".repeat(15), operand);
+    offset + 2
+}
+
+pub fn jump_instruction(op: OpCode, forward: bool, offset: usize, chunk: &Chunk) -> usize {
+    let jump = chunk.bytecode[offset + 1];
+    let target = if forward {
+        offset + 2 + jump
+    } else {
+        offset + 2 - jump
+    };
+    println!("{}{}{} -> {}", op, " ".repeat(15), offset, target);
+    offset + 2
+}
+
+pub fn unpack_rest_instruction(op: OpCode, offset: usize, chunk: &Chunk) -> usize {
+    let before = chunk.bytecode[offset + 1];
+    let after = chunk.bytecode[offset + 2];
+    println!("{}{}{} ... {}", op, " ".repeat(15), before, after);
+    offset + 3
+}
+
+pub fn match_instruction(op: OpCode, offset: usize, chunk: &Chunk) -> usize {
+    let pattern = chunk.bytecode[offset + 1];
+    let jump = chunk.bytecode[offset + 2];
+    println!(
+        "{}{}{:?} else {} -> {}",
+        op,
+        " ".repeat(15),
+        chunk.const_pool.values[pattern].as_pattern(),
+        offset,
+        offset + 3 + jump
+    );
+    offset + 3
+}
+
+pub fn invoke_instruction(op: OpCode, offset: usize, chunk: &Chunk) -> usize {
+    let constant = chunk.bytecode[offset + 1];
+    let arg_count = chunk.bytecode[offset + 2];
+    let name = chunk.const_pool.values[constant].clone();
+    println!("{}{}({} args) '{}'", op, " ".repeat(15), arg_count, name);
+    offset + 3
+}
+
 pub fn constant_instruction(op: OpCode, offset: usize, chunk: &Chunk) -> usize {
     println!(
         "{:?}, offset: {}, const_pool: {:?}",
@@ -423,7 +785,7 @@ pub fn constant_instruction(op: OpCode, offset: usize, chunk: &Chunk) -> usize {
     let constant = chunk.bytecode[offset + 1];
     let val = chunk.const_pool.values[constant].clone();
 
-    println!("{}{}'{}'", op, " ".repeat(15), val);
+    println!("{}{}'{}'", op, " ".repeat(15), val.repr());
     offset + 2
 }
 
@@ -461,17 +823,20 @@ impl VirtualMachineStack {
         self.values[self.ptr - 1 - distance].clone()
     }
 
+    pub fn peek_mut(&mut self, distance: usize) -> &mut GenericValue {
+        if self.ptr == 0 {
+            panic!("[Peek] Invalid operation, empty stack ")
+        }
+        &mut self.values[self.ptr - 1 - distance]
+    }
+
     // Special optimization for OP_NEGATE
-    pub fn negate_peek(&mut self) {
+    pub fn negate_peek(&mut self) -> Result<(), RuntimeError> {
         if self.ptr == 0 {
             panic!("[negate_peek] Invalid operation, empty stack ")
         }
-        let v = -self.values[self.ptr - 1].clone();
-        match v {
-            // TODO: put the actual line, not 0
-            Ok(v) => self.values[self.ptr - 1] = v,
-            Err(e) => runtime_error(0, e.to_string().as_str()),
-        }
+        self.values[self.ptr - 1] = (-self.values[self.ptr - 1].clone())?;
+        Ok(())
     }
 }
 
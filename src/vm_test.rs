@@ -0,0 +1,988 @@
+#[cfg(test)]
+mod test {
+    use crate::chunk::Chunk;
+    use crate::compiler::compile;
+    use crate::values::GenericValue;
+    use crate::vm::{InterpretResult, VirtualMachine};
+
+    fn interpret(s: &str) -> (VirtualMachine, InterpretResult) {
+        let mut vm = VirtualMachine::new();
+        let mut chunk = Chunk::default();
+        assert!(compile(s.to_string(), &mut chunk), "compile error");
+        let result = vm.run(&mut chunk);
+        (vm, result)
+    }
+
+    fn run(s: &str) -> VirtualMachine {
+        let (vm, result) = interpret(s);
+        assert_eq!(result, InterpretResult::InterpretOk);
+        vm
+    }
+
+    fn global(vm: &VirtualMachine, name: &str) -> GenericValue {
+        vm.table
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| panic!("global {} should be defined", name))
+    }
+
+    fn string(s: &str) -> GenericValue {
+        GenericValue::from_string(s.to_string())
+    }
+
+    #[test]
+    fn string_concatenation() {
+        let vm = run("var s = \"ab\" + \"cd\";");
+        assert_eq!(global(&vm, "s"), string("abcd"));
+    }
+
+    #[test]
+    fn string_interpolation() {
+        let vm =
+            run("var name = \"lo\"; var age = 20; var s = \"Hello ${name}, you are ${age + 1}\";");
+        assert_eq!(global(&vm, "s"), string("Hello lo, you are 21"));
+    }
+
+    #[test]
+    fn nested_string_interpolation() {
+        let vm = run("var a = 1; var s = \"<${\"[${a}]\" + \"!\"}>\";");
+        assert_eq!(global(&vm, "s"), string("<[1]!>"));
+    }
+
+    #[test]
+    fn interpolation_missing_end() {
+        let mut chunk = Chunk::default();
+        assert!(!compile("var s = \"${1 2}\";".to_string(), &mut chunk));
+    }
+
+    #[test]
+    fn utf8_source() {
+        let vm = run("var 名字 = \"世界\"; var s = \"你好 ${名字}\";");
+        assert_eq!(global(&vm, "s"), string("你好 世界"));
+    }
+
+    #[test]
+    fn string_method_call() {
+        let vm = run("var s = \"a,b\".split(\",\"); var n = \"你好\".len() + 1; var j = \"-\".join([\"x\", \"y\"]).upper();");
+        assert_eq!(
+            global(&vm, "s"),
+            GenericValue::from_list(vec![string("a"), string("b")])
+        );
+        assert_eq!(global(&vm, "n"), GenericValue::from_f64(3.0));
+        assert_eq!(global(&vm, "j"), string("X-Y"));
+    }
+
+    #[test]
+    fn string_method_error() {
+        let (_, result) = interpret("var s = \"abc\".substring(5);");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret("var s = \"abc\".nope();");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn range_value() {
+        let vm = run("var r = 1..3; var same = r == 1..3; var other = r == 1..=3;");
+        assert_eq!(global(&vm, "r"), GenericValue::from_range(1.0, 3.0, false));
+        assert_eq!(global(&vm, "same"), GenericValue::from_bool(true));
+        assert_eq!(global(&vm, "other"), GenericValue::from_bool(false));
+    }
+
+    #[test]
+    fn for_in_range() {
+        let vm = run("var sum = 0; for (i in 1..4) sum = sum + i; var inclusive = 0; for (i in 1..=4) inclusive = inclusive + i;");
+        assert_eq!(global(&vm, "sum"), GenericValue::from_f64(6.0));
+        assert_eq!(global(&vm, "inclusive"), GenericValue::from_f64(10.0));
+    }
+
+    #[test]
+    fn for_in_empty_range() {
+        let vm = run("var n = 0; for (i in 3..1) n = n + 1; for (i in 2..2) n = n + 1;");
+        assert_eq!(global(&vm, "n"), GenericValue::from_f64(0.0));
+    }
+
+    #[test]
+    fn for_in_string_and_list() {
+        let vm = run(
+            "var s = \"\"; for (c in \"héllo\") s = c + s; var n = 0; for (var x in [1, 2, 3]) { n = n + x; n = n * 2; }",
+        );
+        assert_eq!(global(&vm, "s"), string("olléh"));
+        assert_eq!(global(&vm, "n"), GenericValue::from_f64(22.0));
+    }
+
+    #[test]
+    fn nested_for_in() {
+        let vm = run(
+            "var pairs = \"\"; for (i in 0..2) for (j in \"ab\") pairs = pairs + \"${i}${j} \";",
+        );
+        assert_eq!(global(&vm, "pairs"), string("0a 0b 1a 1b "));
+    }
+
+    #[test]
+    fn for_in_not_iterable() {
+        let (_, result) = interpret("for (x in 1) print x;");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret("var r = \"a\"..2;");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    fn number(n: f64) -> GenericValue {
+        GenericValue::from_f64(n)
+    }
+
+    #[test]
+    fn tuple_literal() {
+        let vm = run("var t = (1, \"a\", (2,)); var empty = (); var group = (1 + 2) * 3;");
+        assert_eq!(
+            global(&vm, "t"),
+            GenericValue::from_tuple(vec![
+                number(1.0),
+                string("a"),
+                GenericValue::from_tuple(vec![number(2.0)])
+            ])
+        );
+        assert_eq!(global(&vm, "empty"), GenericValue::from_tuple(vec![]));
+        assert_eq!(global(&vm, "group"), number(9.0));
+    }
+
+    #[test]
+    fn destructuring_declaration() {
+        let vm = run("var point = (1, 2); var (x, y) = point; var (a, [b, c]) = (3, [4, 5]);");
+        assert_eq!(global(&vm, "x"), number(1.0));
+        assert_eq!(global(&vm, "y"), number(2.0));
+        assert_eq!(global(&vm, "a"), number(3.0));
+        assert_eq!(global(&vm, "b"), number(4.0));
+        assert_eq!(global(&vm, "c"), number(5.0));
+    }
+
+    #[test]
+    fn destructuring_rest() {
+        let vm = run("var [first, ...rest] = [1, 2, 3]; var (head, ...middle, last) = (1, 2, 3, 4); var [...none, end] = [9];");
+        assert_eq!(global(&vm, "first"), number(1.0));
+        assert_eq!(
+            global(&vm, "rest"),
+            GenericValue::from_list(vec![number(2.0), number(3.0)])
+        );
+        assert_eq!(global(&vm, "head"), number(1.0));
+        assert_eq!(
+            global(&vm, "middle"),
+            GenericValue::from_tuple(vec![number(2.0), number(3.0)])
+        );
+        assert_eq!(global(&vm, "last"), number(4.0));
+        assert_eq!(global(&vm, "none"), GenericValue::from_list(vec![]));
+        assert_eq!(global(&vm, "end"), number(9.0));
+    }
+
+    #[test]
+    fn destructuring_assignment() {
+        let vm = run("var x = 1; var y = 2; (x, y) = (y, x); var a; var b; [a, (b, ...x)] = [\"a\", (\"b\", 3)];");
+        assert_eq!(global(&vm, "y"), number(1.0));
+        assert_eq!(global(&vm, "a"), string("a"));
+        assert_eq!(global(&vm, "b"), string("b"));
+        assert_eq!(
+            global(&vm, "x"),
+            GenericValue::from_tuple(vec![number(3.0)])
+        );
+    }
+
+    #[test]
+    fn destructuring_arity_mismatch() {
+        let (_, result) = interpret("var (x, y) = (1, 2, 3);");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret("var [x, y, ...z] = [1];");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret("var (x, y) = 1;");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn destructuring_pattern_error() {
+        let mut chunk = Chunk::default();
+        assert!(!compile("var [...a, ...b] = [1];".to_string(), &mut chunk));
+        let mut chunk = Chunk::default();
+        assert!(!compile("var (a, 1) = (1, 1);".to_string(), &mut chunk));
+    }
+
+    #[test]
+    fn boolean_literals() {
+        let vm = run("var t = true; var f = false; var n = !true;");
+        assert_eq!(global(&vm, "t"), GenericValue::from_bool(true));
+        assert_eq!(global(&vm, "f"), GenericValue::from_bool(false));
+        assert_eq!(global(&vm, "n"), GenericValue::from_bool(false));
+    }
+
+    #[test]
+    fn unary_operand_compiled_once() {
+        let vm = run("var a = -1; var b = -2 * 3; var c = !nil;");
+        assert_eq!(global(&vm, "a"), number(-1.0));
+        assert_eq!(global(&vm, "b"), number(-6.0));
+        assert_eq!(global(&vm, "c"), GenericValue::from_bool(true));
+    }
+
+    const SHAPE: &str = "enum Shape { Circle(r), Rect(w, h), Empty }";
+
+    #[test]
+    fn enum_variants() {
+        let vm = run(&format!(
+            "{} var c = Shape.Circle(2); var e = Shape.Empty; var same = Shape.Rect(1, 2) == Shape.Rect(1, 2); var s = \"${{c}} ${{e}}\";",
+            SHAPE
+        ));
+        assert_eq!(global(&vm, "same"), GenericValue::from_bool(true));
+        assert_eq!(global(&vm, "s"), string("Shape.Circle(2) Shape.Empty"));
+    }
+
+    #[test]
+    fn enum_variant_errors() {
+        let (_, result) = interpret(&format!("{} var c = Shape.Circle;", SHAPE));
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret(&format!("{} var c = Shape.Rect(1);", SHAPE));
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret(&format!("{} var c = Shape.Square(1);", SHAPE));
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn match_variants() {
+        let vm = run(&format!(
+            "{} var total = 0; for (s in [Shape.Circle(1), Shape.Rect(2, 2), Shape.Rect(2, 3), Shape.Empty]) total = total + match s {{ Shape.Circle(r) => 3 * r * r, Shape.Rect(w, h) if w == h => 100, Shape.Rect(w, h) => w * h, Shape.Empty => 0 }};",
+            SHAPE
+        ));
+        assert_eq!(global(&vm, "total"), number(109.0));
+    }
+
+    #[test]
+    fn match_literals_and_wildcards() {
+        let vm = run("var out = \"\"; for (x in [1, -2, \"a\", nil, true, 7]) out = out + match x { 1 => \"one \", -2 => \"minus two \", \"a\" => \"a \", nil => \"nil \", true => \"true \", _ => \"other\" };");
+        assert_eq!(global(&vm, "out"), string("one minus two a nil true other"));
+    }
+
+    #[test]
+    fn match_nested_patterns() {
+        let vm = run("enum Opt { Some(v), None } var a = match Opt.Some(Opt.Some(3)) { Opt.Some(Opt.Some(x)) => x, Opt.Some(Opt.None) => 0, Opt.Some(x) => -1, Opt.None => -2 }; var b = match Opt.Some(5) { Opt.Some(0) => \"zero\", Opt.Some(n) => n };");
+        assert_eq!(global(&vm, "a"), number(3.0));
+        assert_eq!(global(&vm, "b"), number(5.0));
+    }
+
+    #[test]
+    fn match_guard_falls_through() {
+        let vm = run("var x = 5; var r = match x { n if n == 4 => \"four\", n if n == 5 => \"five\", _ => \"other\" };");
+        assert_eq!(global(&vm, "r"), string("five"));
+        assert_eq!(global(&vm, "n"), number(5.0));
+    }
+
+    #[test]
+    fn match_without_matching_arm() {
+        let (_, result) = interpret("var r = match 3 { 1 => 1, 2 => 2 };");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn match_non_exhaustive_still_compiles() {
+        let vm = run(&format!(
+            "{} var r = match Shape.Empty {{ Shape.Empty => 0, Shape.Circle(r) => r }};",
+            SHAPE
+        ));
+        assert_eq!(global(&vm, "r"), number(0.0));
+    }
+
+    #[test]
+    fn match_pattern_errors() {
+        let mut chunk = Chunk::default();
+        assert!(!compile(
+            format!("{} var r = match 1 {{ Shape.Square => 1 }};", SHAPE),
+            &mut chunk
+        ));
+        let mut chunk = Chunk::default();
+        assert!(!compile(
+            format!("{} var r = match 1 {{ Shape.Rect(w) => 1 }};", SHAPE),
+            &mut chunk
+        ));
+        let mut chunk = Chunk::default();
+        assert!(!compile(
+            "var r = match 1 { 1 + 1 => 1 };".to_string(),
+            &mut chunk
+        ));
+    }
+
+    #[test]
+    fn set_literal_deduplicates() {
+        let vm = run("var s = #{1, 2, 1, 3, 2}; var n = s.len(); var t = \"${s}\";");
+        assert_eq!(global(&vm, "n"), number(3.0));
+        assert_eq!(global(&vm, "t"), string("#{1, 2, 3}"));
+    }
+
+    #[test]
+    fn set_constructor() {
+        let vm = run("var ids = set([3, 1, 3, 2, 1]); var empty = set(); var chars = set(\"aba\"); var t = \"${ids} ${empty} ${chars}\";");
+        assert_eq!(global(&vm, "t"), string(r#"#{3, 1, 2} #{} #{"a", "b"}"#));
+    }
+
+    #[test]
+    fn set_methods_change_the_set() {
+        let vm = run("var s = #{1}; var alias = s; var a = s.add(2); var b = s.add(1); var r = alias.remove(1); var has = s.contains(2); var gone = s.contains(1);");
+        assert_eq!(global(&vm, "a"), GenericValue::from_bool(true));
+        assert_eq!(global(&vm, "b"), GenericValue::from_bool(false));
+        assert_eq!(global(&vm, "r"), GenericValue::from_bool(true));
+        assert_eq!(global(&vm, "has"), GenericValue::from_bool(true));
+        assert_eq!(global(&vm, "gone"), GenericValue::from_bool(false));
+    }
+
+    #[test]
+    fn set_operators() {
+        let vm = run("var a = #{1, 2, 3}; var b = #{2, 3, 4}; var u = \"${a | b}\"; var i = \"${a & b}\"; var d = \"${a - b}\"; var p = \"${a | b & #{1}}\";");
+        assert_eq!(global(&vm, "u"), string("#{1, 2, 3, 4}"));
+        assert_eq!(global(&vm, "i"), string("#{2, 3}"));
+        assert_eq!(global(&vm, "d"), string("#{1}"));
+        // & binds tighter than |
+        assert_eq!(global(&vm, "p"), string("#{1, 2, 3}"));
+    }
+
+    #[test]
+    fn set_equality_ignores_order() {
+        let vm = run("var e = #{1, 2} == #{2, 1}; var n = #{1, 2} == #{1, 3};");
+        assert_eq!(global(&vm, "e"), GenericValue::from_bool(true));
+        assert_eq!(global(&vm, "n"), GenericValue::from_bool(false));
+    }
+
+    #[test]
+    fn set_of_compound_values() {
+        let vm = run(&format!(
+            "{} var s = #{{(1, \"a\"), (1, \"a\"), [1, 2], Shape.Circle(1), Shape.Circle(1), 0, -0}}; var n = s.len();",
+            SHAPE
+        ));
+        assert_eq!(global(&vm, "n"), number(4.0));
+    }
+
+    #[test]
+    fn set_iteration() {
+        let vm = run(
+            "var total = 0; var s = #{1, 2, 3}; for (x in s) { total = total + x; s.add(x + 10); }",
+        );
+        assert_eq!(global(&vm, "total"), number(6.0));
+    }
+
+    #[test]
+    fn set_errors() {
+        let (_, result) = interpret("var s = #{#{1}};");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret("var s = #{1}; s.add([#{1}]);");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret("var s = #{1} | [1];");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret("var s = 1; s(2);");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    fn bytes(b: &[u8]) -> GenericValue {
+        GenericValue::from_bytes(b.to_vec())
+    }
+
+    #[test]
+    fn bytes_literal_escapes() {
+        let vm = run(r#"var b = b"a\x00\xff\n\"\\"; var n = b.len(); var t = "${b}";"#);
+        assert_eq!(global(&vm, "b"), bytes(b"a\x00\xff\n\"\\"));
+        assert_eq!(global(&vm, "n"), number(6.0));
+        assert_eq!(global(&vm, "t"), string(r#"b"a\x00\xff\n\"\\""#));
+    }
+
+    #[test]
+    fn bytes_literal_errors() {
+        for source in [
+            r#"var b = b"\x4";"#,
+            r#"var b = b"\q";"#,
+            r#"var b = b"é";"#,
+        ] {
+            let mut chunk = Chunk::default();
+            assert!(!compile(source.to_string(), &mut chunk), "{}", source);
+        }
+    }
+
+    #[test]
+    fn bytes_indexing_and_slicing() {
+        let vm = run(
+            r#"var b = b"\x01\x02\x03\x04"; var first = b[0]; var last = b[3]; var mid = b[1..3]; var tail = b[2..=3];"#,
+        );
+        assert_eq!(global(&vm, "first"), number(1.0));
+        assert_eq!(global(&vm, "last"), number(4.0));
+        assert_eq!(global(&vm, "mid"), bytes(&[2, 3]));
+        assert_eq!(global(&vm, "tail"), bytes(&[3, 4]));
+    }
+
+    #[test]
+    fn indexing_other_sequences() {
+        let vm = run(
+            r#"var l = [1, 2, 3][1]; var t = (1, 2, 3)[0..2]; var s = "héllo"[1]; var sub = "héllo"[1..3];"#,
+        );
+        assert_eq!(global(&vm, "l"), number(2.0));
+        assert_eq!(
+            global(&vm, "t"),
+            GenericValue::from_tuple(vec![number(1.0), number(2.0)])
+        );
+        assert_eq!(global(&vm, "s"), string("é"));
+        assert_eq!(global(&vm, "sub"), string("él"));
+    }
+
+    #[test]
+    fn bytes_concatenation_and_equality() {
+        let vm = run(
+            r#"var b = b"ab" + b"cd"; var e = b == b"abcd"; var n = b == b"abc"; var s = b == "abcd";"#,
+        );
+        assert_eq!(global(&vm, "b"), bytes(b"abcd"));
+        assert_eq!(global(&vm, "e"), GenericValue::from_bool(true));
+        assert_eq!(global(&vm, "n"), GenericValue::from_bool(false));
+        assert_eq!(global(&vm, "s"), GenericValue::from_bool(false));
+    }
+
+    #[test]
+    fn bytes_conversions() {
+        let vm = run(
+            r#"var e = "hé".encode(); var h = e.hex(); var d = from_hex(h).utf8(); var l = bytes([104, 105]); var c = bytes("hé") == e;"#,
+        );
+        assert_eq!(global(&vm, "e"), bytes("hé".as_bytes()));
+        assert_eq!(global(&vm, "h"), string("68c3a9"));
+        assert_eq!(global(&vm, "d"), string("hé"));
+        assert_eq!(global(&vm, "l"), bytes(b"hi"));
+        assert_eq!(global(&vm, "c"), GenericValue::from_bool(true));
+    }
+
+    #[test]
+    fn bytes_iteration() {
+        let vm = run(r#"var total = 0; for (b in b"\x01\x02\x03") total = total + b;"#);
+        assert_eq!(global(&vm, "total"), number(6.0));
+    }
+
+    #[test]
+    fn buffer_is_mutable() {
+        let vm = run(
+            r#"var buf = buffer(b"ab"); var alias = buf; buf.push(99); alias.extend(b"de"); buf[0] = 65; var frozen = buf.to_bytes(); buf[1] = 66; var t = "${buf}";"#,
+        );
+        assert_eq!(global(&vm, "frozen"), bytes(b"Abcde"));
+        assert_eq!(global(&vm, "t"), string(r#"buffer(b"ABcde")"#));
+    }
+
+    #[test]
+    fn bytes_errors() {
+        for source in [
+            r#"var b = b"ab"; b[0] = 1;"#,
+            r#"var b = b"ab"[2];"#,
+            r#"var b = b"ab"[1..3];"#,
+            r#"var b = buffer(); b.push(256);"#,
+            r#"var b = bytes([1.5]);"#,
+            r#"var b = from_hex("abc");"#,
+            r#"var b = from_hex("+f");"#,
+            r#"var s = b"\xff".utf8();"#,
+            r#"var s = #{buffer()};"#,
+        ] {
+            let (_, result) = interpret(source);
+            assert_eq!(result, InterpretResult::InterpretRunTimeError, "{}", source);
+        }
+    }
+
+    #[test]
+    fn optional_chaining_on_nil() {
+        let vm = run(
+            r#"var a = nil; var p = a?.len; var m = a?.upper().len(); var i = a?.[0]; var t = "${p} ${m} ${i}";"#,
+        );
+        assert_eq!(global(&vm, "t"), string("nil nil nil"));
+    }
+
+    #[test]
+    fn optional_chaining_on_values() {
+        let vm = run(
+            r#"var s = "abc"; var m = s?.upper(); var i = s?.[1]; var n = s?.upper().len() + 1;"#,
+        );
+        assert_eq!(global(&vm, "m"), string("ABC"));
+        assert_eq!(global(&vm, "i"), string("b"));
+        assert_eq!(global(&vm, "n"), number(4.0));
+    }
+
+    #[test]
+    fn optional_chain_ends_at_operators() {
+        // the nil short-circuit covers .len() but not the == after it
+        let vm = run("var a = nil; var n = a?.len() == 3;");
+        assert_eq!(global(&vm, "n"), GenericValue::from_bool(false));
+        let (_, result) = interpret("var a = nil; var n = (a?.upper()).len();");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn nil_coalescing() {
+        let vm = run(
+            r#"var a = nil; var b = a ?? "default"; var c = false ?? 1; var d = nil ?? nil ?? 3; var e = a?.len() ?? 0;"#,
+        );
+        assert_eq!(global(&vm, "b"), string("default"));
+        assert_eq!(global(&vm, "c"), GenericValue::from_bool(false));
+        assert_eq!(global(&vm, "d"), number(3.0));
+        assert_eq!(global(&vm, "e"), number(0.0));
+    }
+
+    #[test]
+    fn nil_coalescing_short_circuits() {
+        // the right side would be a runtime error if it ran
+        let vm = run(r#"var a = 1 ?? nil.len(); var b = "x" ?? 1 + "y";"#);
+        assert_eq!(global(&vm, "a"), number(1.0));
+        assert_eq!(global(&vm, "b"), string("x"));
+    }
+
+    #[test]
+    fn nil_coalescing_precedence() {
+        let vm = run("var a = nil ?? 1 + 2; var b = 1 == 2 ?? 3;");
+        assert_eq!(global(&vm, "a"), number(3.0));
+        assert_eq!(global(&vm, "b"), GenericValue::from_bool(false));
+    }
+
+    #[test]
+    fn pipe_into_calls() {
+        let vm = run(
+            r#"var ids = [3, 1, 3, 2] |> set(); var n = ids.len(); var b = "6869" |> from_hex |> buffer(); var t = "${b}";"#,
+        );
+        assert_eq!(global(&vm, "n"), number(3.0));
+        assert_eq!(global(&vm, "t"), string(r#"buffer(b"hi")"#));
+    }
+
+    #[test]
+    fn pipe_binds_loosely() {
+        // the whole left side is piped, not just the last operand
+        let vm = run(r#"var s = nil ?? "ab" + "ba" |> set(); var n = s.len();"#);
+        assert_eq!(global(&vm, "n"), number(2.0));
+    }
+
+    #[test]
+    fn pipe_passes_first_argument() {
+        // set() takes at most one argument, the piped value makes it two
+        let (_, result) = interpret("var s = [1] |> set([2]);");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret("var s = [1] |> 2;");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn pipe_into_subscript() {
+        let vm = run(
+            "var fs = [str, set]; var s = 1 |> fs[0]; var t = [1, 1] |> fs[1](); var n = t.len();",
+        );
+        assert_eq!(global(&vm, "s"), string("1"));
+        assert_eq!(global(&vm, "n"), number(1.0));
+    }
+
+    #[test]
+    fn spread_in_list_literals() {
+        let vm = run(
+            r#"var a = [1, 2]; var b = [0, ...a, 3, ...(4, 5), ...6..8, ..."hé"]; var c = [...[]];"#,
+        );
+        assert_eq!(
+            global(&vm, "b"),
+            GenericValue::from_list(vec![
+                number(0.0),
+                number(1.0),
+                number(2.0),
+                number(3.0),
+                number(4.0),
+                number(5.0),
+                number(6.0),
+                number(7.0),
+                string("h"),
+                string("é"),
+            ])
+        );
+        assert_eq!(global(&vm, "c"), GenericValue::from_list(vec![]));
+    }
+
+    #[test]
+    fn spread_in_calls() {
+        let vm = run(
+            r#"var args = [[1, 1, 2]]; var s = set(...args); var n = s.len(); var r = ", ".join(...[["a", "b"]]); var p = [] |> set(...[]);"#,
+        );
+        assert_eq!(global(&vm, "n"), number(2.0));
+        assert_eq!(global(&vm, "r"), string("a, b"));
+        assert_eq!(global(&vm, "p").to_string(), "#{}");
+    }
+
+    #[test]
+    fn spread_argument_count_is_checked() {
+        let (_, result) = interpret("var s = set(...[1, 2]);");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret(r#"var s = "a".upper(...[1]);"#);
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn spread_non_iterable() {
+        let (_, result) = interpret("var l = [...1];");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn operator_errors_stop_execution() {
+        for source in [
+            r#"var a = nil + 1; var b = 2;"#,
+            r#"var a = "a" - 1; var b = 2;"#,
+            r#"var a = 1 * [1]; var b = 2;"#,
+            r#"var a = 1 / 0; var b = 2;"#,
+            r#"var a = -"a"; var b = 2;"#,
+        ] {
+            let (vm, result) = interpret(source);
+            assert_eq!(result, InterpretResult::InterpretRunTimeError, "{}", source);
+            assert!(vm.table.get("b").is_none(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn display_and_repr() {
+        let vm = run(
+            r#"var s = str("hi"); var r = repr("hi"); var l = str(["a", 1, ("b",)]); var t = "${["x"]} ${nil} ${true}";"#,
+        );
+        assert_eq!(global(&vm, "s"), string("hi"));
+        assert_eq!(global(&vm, "r"), string(r#""hi""#));
+        assert_eq!(global(&vm, "l"), string(r#"["a", 1, ("b",)]"#));
+        assert_eq!(global(&vm, "t"), string(r#"["x"] nil true"#));
+    }
+
+    #[test]
+    fn repr_escapes_strings() {
+        let vm = run(r#"var r = repr(from_hex("0a2209225c").utf8());"#);
+        assert_eq!(global(&vm, "r"), string(r#""\n\"\t\"\\""#));
+    }
+
+    #[test]
+    fn number_formatting() {
+        let vm = run(
+            r#"var t = "${1} ${2.5} ${0.1 + 0.2} ${-0} ${1000000} ${1000000000 * 1000000000 * 1000} ${1 / 10000000} ${10 / 3}";"#,
+        );
+        assert_eq!(
+            global(&vm, "t"),
+            string("1 2.5 0.30000000000000004 -0 1000000 1e21 1e-7 3.3333333333333335")
+        );
+    }
+
+    fn boolean(b: bool) -> GenericValue {
+        GenericValue::from_bool(b)
+    }
+
+    #[test]
+    fn comparison_operand_order() {
+        let vm = run("var a = 1 < 2; var b = 2 > 1; var c = 2 <= 1; var d = 1 >= 1;");
+        assert_eq!(global(&vm, "a"), boolean(true));
+        assert_eq!(global(&vm, "b"), boolean(true));
+        assert_eq!(global(&vm, "c"), boolean(false));
+        assert_eq!(global(&vm, "d"), boolean(true));
+    }
+
+    #[test]
+    fn equality_semantics() {
+        let vm = run(
+            r#"var a = nil == nil; var b = nil != false; var c = 1 == "1"; var d = [1, (2, "x")] == [1, (2, "x")]; var e = (1,) == [1];"#,
+        );
+        assert_eq!(global(&vm, "a"), boolean(true));
+        assert_eq!(global(&vm, "b"), boolean(true));
+        assert_eq!(global(&vm, "c"), boolean(false));
+        assert_eq!(global(&vm, "d"), boolean(true));
+        assert_eq!(global(&vm, "e"), boolean(false));
+    }
+
+    #[test]
+    fn nan_is_unordered() {
+        // 0 / 0 is a division error, nan comes from subtracting two infinities
+        let vm = run("var big = 1000000000 * 1000000000; var inf = big * big * big * big * big * big * big * big * big * big * big * big * big * big * big * big * big * big; var nan = inf - inf; var e = nan == nan; var l = nan < 1; var g = nan >= 1; var n = nan != nan;");
+        assert_eq!(global(&vm, "e"), boolean(false));
+        assert_eq!(global(&vm, "l"), boolean(false));
+        assert_eq!(global(&vm, "g"), boolean(false));
+        assert_eq!(global(&vm, "n"), boolean(true));
+    }
+
+    #[test]
+    fn ordering_of_strings_and_sequences() {
+        let vm = run(
+            r#"var a = "apple" < "banana"; var b = "Z" < "a"; var c = "ab" < "abc"; var d = [1, 2] < [1, 3]; var e = [1, 2] < [1, 2, 0]; var f = (2, "a") > (1, "z"); var g = [nil, 1] < [nil, 2]; var h = b"\x01" < b"\x02";"#,
+        );
+        for name in ["a", "b", "c", "d", "e", "f", "g", "h"] {
+            assert_eq!(global(&vm, name), boolean(true), "{}", name);
+        }
+    }
+
+    #[test]
+    fn incomparable_types() {
+        for source in [
+            r#"var a = 1 < "1";"#,
+            r#"var a = nil < nil;"#,
+            r#"var a = true > false;"#,
+            r#"var a = [1] < (1,);"#,
+            r#"var a = [1, "a"] < [1, 2];"#,
+        ] {
+            let (vm, result) = interpret(source);
+            assert_eq!(result, InterpretResult::InterpretRunTimeError, "{}", source);
+            assert!(vm.table.get("a").is_none(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn not_uses_truthiness() {
+        let vm = run(r#"var a = !nil; var b = !0; var c = !""; var d = !false;"#);
+        assert_eq!(global(&vm, "a"), boolean(true));
+        assert_eq!(global(&vm, "b"), boolean(false));
+        assert_eq!(global(&vm, "c"), boolean(false));
+        assert_eq!(global(&vm, "d"), boolean(true));
+    }
+
+    #[test]
+    fn sorted_values() {
+        let vm = run(
+            r#"var n = sorted([3, 1, 2]); var s = sorted(#{"b", "a", "c"}); var t = sorted([(2, "a"), (1, "b"), (1, "a")]);"#,
+        );
+        assert_eq!(
+            global(&vm, "n"),
+            GenericValue::from_list(vec![number(1.0), number(2.0), number(3.0)])
+        );
+        assert_eq!(global(&vm, "s").to_string(), r#"["a", "b", "c"]"#);
+        assert_eq!(
+            global(&vm, "t").to_string(),
+            r#"[(1, "a"), (1, "b"), (2, "a")]"#
+        );
+        let (_, result) = interpret(r#"var s = sorted([1, "a"]);"#);
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn sorted_checks_every_value_first() {
+        let vm = run("var l = sorted([[nil, 2], [nil, 1], [nil]]);");
+        assert_eq!(global(&vm, "l").to_string(), "[[nil], [nil, 1], [nil, 2]]");
+        // the second elements are never compared, the first ones already differ
+        let vm = run(r#"var lt = [1, "a"] < [2, 3]; var l = sorted([[2, 3], [1, "a"]]);"#);
+        assert_eq!(global(&vm, "lt"), GenericValue::from_bool(true));
+        assert_eq!(global(&vm, "l").to_string(), r#"[[1, "a"], [2, 3]]"#);
+
+        let (_, result) = interpret(r#"var s = sorted([3, 2, 1, "a"]);"#);
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret(r#"var s = sorted([[1, 2], [1, "a"]]);"#);
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        // 10 squared 9 times overflows to inf, and inf - inf is nan
+        let (_, result) = interpret(
+            "var inf = 10; for (i in 0..9) inf = inf * inf; var s = sorted([1, inf - inf, 2]);",
+        );
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret(
+            "var inf = 10; for (i in 0..9) inf = inf * inf; var s = sorted([(1, inf - inf), (1, 2)]);",
+        );
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    fn run_module(
+        dir_name: &str,
+        files: &[(&str, &str)],
+        main: &str,
+    ) -> (VirtualMachine, InterpretResult) {
+        // writes the files into a fresh directory and runs main as if it were a file there
+        let dir = std::env::temp_dir().join(format!("lolang_{}", dir_name));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (path, source) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        let mut vm = VirtualMachine::new();
+        vm.modules.enter(&dir.join("main.lo"));
+        let mut chunk = Chunk::default();
+        assert!(compile(main.to_string(), &mut chunk), "compile error");
+        let result = vm.run(&mut chunk);
+        (vm, result)
+    }
+
+    #[test]
+    fn import_as() {
+        let (vm, result) = run_module(
+            "import_as",
+            &[("lib/math.lo", "var two = 2; enum Sign { Plus, Minus }")],
+            r#"import "lib/math.lo" as math; var a = math.two + 1; var b = math.Sign.Minus;"#,
+        );
+        assert_eq!(result, InterpretResult::InterpretOk);
+        assert_eq!(global(&vm, "math").to_string(), "<module math>");
+        assert_eq!(global(&vm, "a"), number(3.0));
+        assert_eq!(global(&vm, "b").to_string(), "Sign.Minus");
+    }
+
+    #[test]
+    fn pipe_into_module_member() {
+        let (vm, result) = run_module(
+            "pipe_into_module_member",
+            &[("text.lo", r#"var greeting = str; var tag = [str];"#)],
+            r#"import "text.lo" as m; var a = 1 |> m.greeting; var b = 2 |> m.tag[0]();"#,
+        );
+        assert_eq!(result, InterpretResult::InterpretOk);
+        assert_eq!(global(&vm, "a"), string("1"));
+        assert_eq!(global(&vm, "b"), string("2"));
+    }
+
+    #[test]
+    fn from_import() {
+        let (vm, result) = run_module(
+            "from_import",
+            &[("shapes.lo", r#"var pi = 3; var name = "shapes";"#)],
+            r#"from "shapes.lo" import pi, name;"#,
+        );
+        assert_eq!(result, InterpretResult::InterpretOk);
+        assert_eq!(global(&vm, "pi"), number(3.0));
+        assert_eq!(global(&vm, "name"), string("shapes"));
+
+        let (_, result) = run_module(
+            "from_import_missing",
+            &[("shapes.lo", "var pi = 3;")],
+            r#"from "shapes.lo" import tau;"#,
+        );
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn modules_run_once() {
+        // both imports share the module built by the first one
+        let (vm, result) = run_module(
+            "modules_run_once",
+            &[("a.lo", "var x = 1;"), ("b.lo", r#"import "a.lo" as a;"#)],
+            r#"import "a.lo" as a; import "b.lo" as b; var same = a == b.a;"#,
+        );
+        assert_eq!(result, InterpretResult::InterpretOk);
+        assert_eq!(global(&vm, "same"), boolean(true));
+    }
+
+    #[test]
+    fn imports_are_relative_to_the_importing_file() {
+        let (vm, result) = run_module(
+            "relative_imports",
+            &[
+                (
+                    "lib/outer.lo",
+                    r#"from "inner/value.lo" import v; var w = v * 2;"#,
+                ),
+                ("lib/inner/value.lo", "var v = 21;"),
+            ],
+            r#"from "lib/outer.lo" import w;"#,
+        );
+        assert_eq!(result, InterpretResult::InterpretOk);
+        assert_eq!(global(&vm, "w"), number(42.0));
+    }
+
+    #[test]
+    fn import_errors() {
+        let (_, result) = run_module(
+            "circular_import",
+            &[
+                ("a.lo", r#"import "b.lo" as b;"#),
+                ("b.lo", r#"import "a.lo" as a;"#),
+            ],
+            r#"import "a.lo" as a;"#,
+        );
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+
+        let (_, result) = run_module("missing_import", &[], r#"import "nope.lo" as nope;"#);
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+
+        let (_, result) = run_module(
+            "failing_import",
+            &[("bad.lo", "var x = 1 + nil;")],
+            r#"import "bad.lo" as bad;"#,
+        );
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn type_annotations_are_erased() {
+        // a mismatch is only reported by the checker, it runs like any other code
+        let vm = run(r#"var x: Int = "a"; var y: Str? = nil;"#);
+        assert_eq!(global(&vm, "x"), string("a"));
+        assert_eq!(global(&vm, "y"), GenericValue::from_none());
+    }
+
+    #[test]
+    fn records() {
+        let vm = run(
+            "record Point(x, y) var p = Point(1, 2); var sum = p.x + p.y; var same = p == Point(1, 2); var other = p == Point(2, 1); var s = #{p, Point(1, 2)};",
+        );
+        assert_eq!(global(&vm, "p").to_string(), "Point(x: 1, y: 2)");
+        assert_eq!(global(&vm, "sum"), number(3.0));
+        assert_eq!(global(&vm, "same"), boolean(true));
+        assert_eq!(global(&vm, "other"), boolean(false));
+        assert_eq!(global(&vm, "s").to_string(), "#{Point(x: 1, y: 2)}");
+
+        let (_, result) = interpret("record Point(x, y) var p = Point(1);");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret("record Point(x, y) var z = Point(1, 2).z;");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let mut chunk = Chunk::default();
+        assert!(!compile("record Point(x, x)".to_string(), &mut chunk));
+    }
+
+    #[test]
+    fn record_from_module() {
+        let (vm, result) = run_module(
+            "record_from_module",
+            &[("geometry.lo", "record Point(x, y)")],
+            r#"import "geometry.lo" as m; var p = m.Point(1, 2);"#,
+        );
+        assert_eq!(result, InterpretResult::InterpretOk);
+        assert_eq!(global(&vm, "p").to_string(), "Point(x: 1, y: 2)");
+    }
+
+    #[test]
+    fn freeze_values() {
+        let vm = run(
+            "var s = #{1}; var t = s; freeze(t); var keys = #{s, freeze(#{1})}; var u = s | #{2}; u.add(3);",
+        );
+        assert_eq!(global(&vm, "keys").to_string(), "#{#{1}}");
+        assert_eq!(global(&vm, "u").to_string(), "#{1, 2, 3}");
+
+        // a frozen set is frozen for every copy of it
+        let (_, result) = interpret("var s = #{1}; var t = s; freeze(t); s.add(2);");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret("var s = freeze(#{1}); s.remove(1);");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret(r#"var b = freeze(buffer("a")); b[0] = 1;"#);
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret(r#"var b = freeze(buffer("a")); b.push(1);"#);
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) = interpret("var s = #{#{1}};");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+
+        // a set is not hashable while it can change, not even inside itself
+        let (_, result) = interpret("var s = set(); s.add(s);");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let vm = run("var s = set(); s.add(1); var removed = s.remove(s);");
+        assert_eq!(global(&vm, "removed"), boolean(false));
+        let (_, result) = interpret("var f = freeze(#{1}); f.remove(#{1});");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn freeze_nested_values() {
+        let vm = run(
+            "record P(a, b) enum E { V(s) } var l = freeze([#{1}, (#{2}, 3)]); var keys = #{l, freeze(P(#{1}, 2)), freeze(E.V([#{3}]))}; var n = keys.len();",
+        );
+        assert_eq!(global(&vm, "n"), number(3.0));
+        assert_eq!(global(&vm, "l").to_string(), "[#{1}, (#{2}, 3)]");
+
+        // the sets inside stay frozen when they are taken out again
+        let (_, result) = interpret("var l = freeze([#{1}]); var t = l[0]; t.add(2);");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+        let (_, result) =
+            interpret("record P(a, b) var p = freeze(P(1, [buffer(\"a\")])); p.b[0].push(1);");
+        assert_eq!(result, InterpretResult::InterpretRunTimeError);
+    }
+
+    #[test]
+    fn nan_is_not_hashable() {
+        // 10 squared 9 times overflows to inf, and inf - inf is nan
+        let nan = "var n = 10; for (i in 0..9) n = n * n; n = n - n;";
+        for source in [
+            "var s = #{n};",
+            "var s = set(); s.add(n);",
+            "var s = #{[1, n]};",
+        ] {
+            let (_, result) = interpret(&format!("{} {}", nan, source));
+            assert_eq!(result, InterpretResult::InterpretRunTimeError, "{}", source);
+        }
+        let vm = run(&format!(
+            "{} var s = #{{1}}; var has = s.contains(n); var removed = s.remove(n);",
+            nan
+        ));
+        assert_eq!(global(&vm, "has"), boolean(false));
+        assert_eq!(global(&vm, "removed"), boolean(false));
+    }
+}